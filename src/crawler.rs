@@ -0,0 +1,178 @@
+use std::collections::HashSet;
+use std::time::Instant;
+use futures::stream::{self, StreamExt};
+use scraper::{Html, Selector};
+use url::Url;
+use crate::http::{ErrorClass, HttpClient};
+use crate::report::ProbeRecord;
+use crate::robots::RobotsGroup;
+use crate::session;
+use crate::state;
+
+pub struct CrawlConfig {
+    pub max_depth: usize,
+    pub max_pages: usize,
+    pub allowed_hosts: HashSet<String>,
+    pub concurrency: usize,
+    //rules of the selected robots.txt group when robots.txt is respected
+    pub robots: Option<RobotsGroup>,
+    //logged in: links that look like a logout are not followed
+    pub keep_session: bool,
+}
+
+//resolves every followable link of a page against the page URL
+pub fn extract_links(page_url: &Url, html: &str) -> Vec<Url> {
+    let document = Html::parse_document(html);
+    let link_selector = Selector::parse("a[href], area[href], iframe[src], frame[src]").unwrap();
+
+    let mut links = Vec::new();
+    for element in document.select(&link_selector) {
+        let target = element
+            .value()
+            .attr("href")
+            .or_else(|| element.value().attr("src"));
+
+        if let Some(target) = target
+            && let Ok(mut link) = page_url.join(target.trim())
+            && matches!(link.scheme(), "http" | "https")
+        {
+            //fragments point into the same document, so they are dropped for de-duplication
+            link.set_fragment(None);
+            links.push(link);
+        }
+    }
+    links
+}
+
+//a link is in scope if it shares the target origin or its host is on the allow-list,
+//robots.txt (when respected) does not disallow it and it does not end the session
+fn is_in_scope(url: &Url, origin: &Url, config: &CrawlConfig) -> bool {
+    let host_allowed = url.origin() == origin.origin()
+        || url
+            .host_str()
+            .is_some_and(|host| config.allowed_hosts.contains(&host.to_ascii_lowercase()));
+    if !host_allowed || (config.keep_session && session::is_logout_path(url.path())) {
+        return false;
+    }
+
+    config.robots.as_ref().is_none_or(|group| {
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        group.is_allowed(&path)
+    })
+}
+
+//Returns the URL the request landed on after following redirects, with the record of the
+//requested URL; links of the page are relative to the landing URL.
+async fn fetch_html(client: &HttpClient, url: Url) -> (Url, ProbeRecord, Option<String>) {
+    let started = Instant::now();
+    match client.get(url.as_str()).await {
+        Ok(resp) => {
+            let mut record = ProbeRecord::from_response(url.as_str(), &resp, started);
+            if !resp.status().is_success() {
+                println!("Status {}: {}", resp.status(), url);
+                return (url, record, None);
+            }
+            let landing = resp.url().clone();
+
+            let is_html = record
+                .content_type
+                .as_deref()
+                .is_some_and(|ct| ct.contains("text/html"));
+            if !is_html {
+                return (landing, record, None);
+            }
+
+            match resp.text().await {
+                Ok(html) => {
+                    record.size = Some(html.len() as u64);
+                    (landing, record, Some(html))
+                }
+                Err(e) => {
+                    eprintln!("Failed to get text from {}: {}", url, e);
+                    record.set_error(&e);
+                    (landing, record, None)
+                }
+            }
+        }
+        Err(e) => {
+            println!("Request failed for {} ({}): {}", url, ErrorClass::classify(&e), e);
+            let record = ProbeRecord::from_error(url.as_str(), &e, started);
+            (url, record, None)
+        }
+    }
+}
+
+//Breadth-first crawl starting from pages that were already fetched (`seeds`); links to `fetched`
+//URLs, requested before the crawl for other reasons, are not followed again.
+//Every request is handed to `on_page` with the URL it landed on, and the body for HTML pages;
+//returns the number of pages fetched.
+pub async fn crawl<F>(
+    client: &HttpClient,
+    origin: &Url,
+    seeds: Vec<(Url, String)>,
+    fetched: HashSet<Url>,
+    config: &CrawlConfig,
+    mut on_page: F,
+) -> usize
+where
+    F: FnMut(&Url, ProbeRecord, Option<String>) -> Result<(), Box<dyn std::error::Error>>,
+{
+    let mut visited = fetched;
+    visited.extend(seeds.iter().map(|(url, _)| url.clone()));
+    let mut frontier = Vec::new();
+
+    for (url, html) in &seeds {
+        for link in extract_links(url, html) {
+            if is_in_scope(&link, origin, config) && visited.insert(link.clone()) {
+                frontier.push(link);
+            }
+        }
+    }
+
+    let mut pages_fetched = 0;
+    let mut depth = 1;
+
+    while !frontier.is_empty() && depth <= config.max_depth && pages_fetched < config.max_pages {
+        frontier.truncate(config.max_pages - pages_fetched);
+        println!("Crawling depth {} ({} new pages)...", depth, frontier.len());
+
+        let mut next_frontier = Vec::new();
+        let mut pages = stream::iter(frontier)
+            .map(|url| fetch_html(client, url))
+            .buffered(config.concurrency.max(1));
+
+        while let Some((url, record, html)) = tokio::select! {
+            page = pages.next() => page,
+            _ = state::wait_for_interrupt() => None,
+        } {
+            //a redirect to a page that is known already only adds the request, the page
+            //is not scanned twice; one that is new is not fetched again when linked directly
+            let landed_elsewhere = record.url != url.as_str();
+            let html = html.filter(|_| !landed_elsewhere || visited.insert(url.clone()));
+            if let Some(html) = &html {
+                pages_fetched += 1;
+
+                for link in extract_links(&url, html) {
+                    if is_in_scope(&link, origin, config) && visited.insert(link.clone()) {
+                        next_frontier.push(link);
+                    }
+                }
+            }
+
+            if let Err(e) = on_page(&url, record, html) {
+                eprintln!("Error processing {}: {}", url, e);
+            }
+        }
+
+        if state::interrupted() {
+            break;
+        }
+        frontier = next_frontier;
+        depth += 1;
+    }
+
+    pages_fetched
+}
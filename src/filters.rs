@@ -0,0 +1,145 @@
+use regex::Regex;
+
+//statuses kept when --match-codes is not given: successes, redirects and the
+//error statuses that point at something that exists
+pub const DEFAULT_MATCH_CODES: &str = "200-299,300-399,401,403,405,500";
+
+//"200,204,300-399" or "all", for status codes, sizes and word counts
+#[derive(Debug, Clone)]
+pub struct NumberRanges(Vec<(u64, u64)>);
+
+impl NumberRanges {
+    pub fn contains(&self, value: u64) -> bool {
+        self.0.iter().any(|(low, high)| (*low..=*high).contains(&value))
+    }
+}
+
+//clap value parser for the code, size and word lists
+pub fn parse_ranges(value: &str) -> Result<NumberRanges, String> {
+    let mut ranges = Vec::new();
+    for part in value.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        if part.eq_ignore_ascii_case("all") {
+            ranges.push((0, u64::MAX));
+            continue;
+        }
+        let (low, high) = part.split_once('-').unwrap_or((part, part));
+        let number = |text: &str| {
+            text.trim()
+                .parse::<u64>()
+                .map_err(|_| format!("'{}' is not a number or a range like 200-299", part))
+        };
+        let (low, high) = (number(low)?, number(high)?);
+        if low > high {
+            return Err(format!("'{}' is an empty range", part));
+        }
+        ranges.push((low, high));
+    }
+    if ranges.is_empty() {
+        return Err("expected a list like 200,301-399 or 'all'".to_string());
+    }
+    Ok(NumberRanges(ranges))
+}
+
+//Decides which responses are kept, like the matchers and filters of a content discoverer:
+//a response is kept when its status is matched, --match-regex (if given) finds the body
+//and none of the filters applies.
+#[derive(Debug, Clone)]
+pub struct ResponseFilter {
+    pub match_codes: NumberRanges,
+    pub filter_codes: Option<NumberRanges>,
+    pub filter_sizes: Option<NumberRanges>,
+    pub filter_words: Option<NumberRanges>,
+    pub filter_regex: Option<Regex>,
+    pub match_regex: Option<Regex>,
+}
+
+impl ResponseFilter {
+    pub fn matches_status(&self, status: u16) -> bool {
+        self.match_codes.contains(status as u64)
+            && !self.filter_codes.as_ref().is_some_and(|codes| codes.contains(status as u64))
+    }
+
+    //`body` is None when it was not downloaded, then only the size is checked
+    pub fn matches_body(&self, size: usize, body: Option<&str>) -> bool {
+        if self.filter_sizes.as_ref().is_some_and(|sizes| sizes.contains(size as u64)) {
+            return false;
+        }
+        let Some(body) = body else {
+            return self.match_regex.is_none();
+        };
+        if self
+            .filter_words
+            .as_ref()
+            .is_some_and(|words| words.contains(body.split_whitespace().count() as u64))
+        {
+            return false;
+        }
+        if self.filter_regex.as_ref().is_some_and(|re| re.is_match(body)) {
+            return false;
+        }
+        self.match_regex.as_ref().is_none_or(|re| re.is_match(body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter() -> ResponseFilter {
+        ResponseFilter {
+            match_codes: parse_ranges(DEFAULT_MATCH_CODES).unwrap(),
+            filter_codes: None,
+            filter_sizes: None,
+            filter_words: None,
+            filter_regex: None,
+            match_regex: None,
+        }
+    }
+
+    #[test]
+    fn ranges_accept_codes_ranges_and_all() {
+        let ranges = parse_ranges("200, 301-399").unwrap();
+        assert!(ranges.contains(200) && ranges.contains(301) && ranges.contains(399));
+        assert!(!ranges.contains(300) && !ranges.contains(404));
+        assert!(parse_ranges("ALL").unwrap().contains(999));
+    }
+
+    #[test]
+    fn ranges_reject_garbage() {
+        assert!(parse_ranges("").is_err());
+        assert!(parse_ranges("abc").is_err());
+        assert!(parse_ranges("399-300").is_err());
+        assert!(parse_ranges("200-").is_err());
+    }
+
+    #[test]
+    fn filter_codes_win_over_match_codes() {
+        let mut filter = filter();
+        assert!(filter.matches_status(403) && !filter.matches_status(404));
+        filter.filter_codes = Some(parse_ranges("403").unwrap());
+        assert!(!filter.matches_status(403) && filter.matches_status(200));
+    }
+
+    #[test]
+    fn body_filters_check_size_words_and_patterns() {
+        let mut filter = filter();
+        filter.filter_sizes = Some(parse_ranges("0").unwrap());
+        filter.filter_words = Some(parse_ranges("2").unwrap());
+        filter.filter_regex = Some(Regex::new("(?i)not found").unwrap());
+        assert!(!filter.matches_body(0, Some("")));
+        assert!(!filter.matches_body(9, Some("two words")));
+        assert!(!filter.matches_body(22, Some("Page was Not Found ok?")));
+        assert!(filter.matches_body(12, Some("one two three")));
+        //a body that was not downloaded is only checked by its size
+        assert!(filter.matches_body(5000, None));
+    }
+
+    #[test]
+    fn match_regex_needs_the_body() {
+        let mut filter = filter();
+        filter.match_regex = Some(Regex::new("admin").unwrap());
+        assert!(filter.matches_body(10, Some("admin area")));
+        assert!(!filter.matches_body(10, Some("home page")));
+        assert!(!filter.matches_body(10, None));
+    }
+}
@@ -0,0 +1,152 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::LazyLock;
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use crate::report::ProbeRecord;
+use crate::scanner::ScannerInfos;
+use crate::technologies::{self, Evidence};
+
+//name of the header log written next to the saved pages, picked up by scan_all_html_files
+pub const HEADER_LOG_FILE: &str = "responses.headers";
+
+//headers whose value names the software that answered
+const PRODUCT_HEADERS: &[(&str, &str)] = &[
+    ("server", "Server"),
+    ("x-powered-by", "X-Powered-By"),
+    ("via", "Via"),
+    ("x-aspnet-version", "X-AspNet-Version"),
+    ("x-aspnetmvc-version", "X-AspNetMvc-Version"),
+    ("x-generator", "X-Generator"),
+    ("x-served-by", "X-Served-By"),
+];
+
+//headers whose mere presence identifies a product
+const MARKER_HEADERS: &[(&str, &str)] = &[
+    ("cf-ray", "Cloudflare"),
+    ("x-amz-cf-id", "Amazon CloudFront"),
+    ("x-amz-request-id", "Amazon S3"),
+    ("x-azure-ref", "Azure Front Door"),
+    ("x-varnish", "Varnish"),
+    ("x-drupal-cache", "Drupal"),
+    ("x-drupal-dynamic-cache", "Drupal"),
+    ("x-pingback", "WordPress"),
+    ("x-litespeed-cache", "LiteSpeed Cache"),
+    ("x-shopify-stage", "Shopify"),
+    ("x-wix-request-id", "Wix"),
+    ("x-runtime", "Ruby on Rails"),
+    ("x-fastly-request-id", "Fastly"),
+];
+
+//session cookie name prefixes and the framework that sets them
+const COOKIE_FRAMEWORKS: &[(&str, &str)] = &[
+    ("PHPSESSID", "PHP"),
+    ("JSESSIONID", "Java Servlet"),
+    ("ASP.NET_SessionId", "ASP.NET"),
+    ("ASPSESSIONID", "Classic ASP"),
+    (".AspNetCore.", "ASP.NET Core"),
+    ("laravel_session", "Laravel"),
+    ("csrftoken", "Django"),
+    ("connect.sid", "Express"),
+    ("ci_session", "CodeIgniter"),
+    ("wordpress_", "WordPress"),
+    ("wp-settings-", "WordPress"),
+    ("symfony", "Symfony"),
+    ("PrestaShop-", "PrestaShop"),
+    ("__cf_bm", "Cloudflare"),
+    ("AWSALB", "AWS Elastic Load Balancer"),
+    ("BIGipServer", "F5 BIG-IP"),
+];
+
+pub fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.as_str().to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+//"Apache/2.4.58", "PHP/8.2.1" in Server, X-Powered-By and the like
+static PRODUCT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"([A-Za-z][\w.\-]*)/(\d[\w.\-]*)").unwrap());
+
+fn add_finding(info: &mut ScannerInfos, finding: String) {
+    info.count("webserver", &finding);
+    info.webserver.insert(finding);
+}
+
+//turns response headers into webserver, framework and version findings
+pub fn fingerprint_headers(headers: &[(String, String)]) -> ScannerInfos {
+    let mut info = ScannerInfos::default();
+
+    for (name, value) in headers {
+        let name_lower = name.to_ascii_lowercase();
+        let value = value.trim();
+
+        if let Some((_, label)) = PRODUCT_HEADERS.iter().find(|(header, _)| *header == name_lower) {
+            add_finding(&mut info, format!("{}: {}", label, value));
+            for cap in PRODUCT.captures_iter(value) {
+                add_finding(&mut info, format!("Version: {} {}", &cap[1], &cap[2]));
+            }
+            match name_lower.as_str() {
+                "x-aspnet-version" => add_finding(&mut info, format!("Version: ASP.NET {}", value)),
+                "x-aspnetmvc-version" => add_finding(&mut info, format!("Version: ASP.NET MVC {}", value)),
+                _ => {}
+            }
+        }
+
+        if let Some((_, product)) = MARKER_HEADERS.iter().find(|(header, _)| *header == name_lower) {
+            add_finding(&mut info, format!("Framework: {} ({} header)", product, name));
+        }
+
+        if name_lower == "set-cookie"
+            && let Some((cookie_name, _)) = value.split_once('=')
+        {
+            let cookie_name = cookie_name.trim();
+            add_finding(&mut info, format!("Cookie: {}", cookie_name));
+            if let Some((_, framework)) = COOKIE_FRAMEWORKS
+                .iter()
+                .find(|(prefix, _)| cookie_name.starts_with(prefix))
+            {
+                add_finding(&mut info, format!("Framework: {} ({} cookie)", framework, cookie_name));
+            }
+        }
+    }
+
+    for technology in technologies::detect(&Evidence::from_headers(headers)) {
+        info.add_technology(technology);
+    }
+
+    info
+}
+
+//writes the headers of every probe as "# url" followed by "Name: value" lines
+pub fn write_header_log(dir: &Path, probes: &[ProbeRecord]) -> Result<(), std::io::Error> {
+    let path = dir.join(HEADER_LOG_FILE);
+    let mut writer = BufWriter::new(File::create(&path)?);
+    for probe in probes.iter().filter(|probe| !probe.headers.is_empty()) {
+        writeln!(writer, "# {}", probe.url)?;
+        for (name, value) in &probe.headers {
+            writeln!(writer, "{}: {}", name, value)?;
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    println!("Response headers saved in {}", path.display());
+    Ok(())
+}
+
+pub fn scan_header_log(path: &Path) -> Result<ScannerInfos, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    let headers: Vec<(String, String)> = content
+        .lines()
+        .filter(|line| !line.starts_with("# "))
+        .filter_map(|line| line.split_once(": "))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    Ok(fingerprint_headers(&headers))
+}
@@ -0,0 +1,982 @@
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error as _;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use indicatif::ProgressBar;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::cookie::{CookieStore, Jar};
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue, LOCATION, RETRY_AFTER, USER_AGENT};
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, Client, Proxy, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use url::Url;
+
+//waits announced by the server beyond this are capped, a day-long ban is not worth waiting out
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+//the error rate is judged over this many responses per host
+const ERROR_WINDOW: usize = 20;
+const BACKOFF_START: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+//the progress bar of the running scan, retry and backoff notices are printed above it
+static PROGRESS_BAR: RwLock<Option<ProgressBar>> = RwLock::new(None);
+
+//set while a progress bar is drawn, None once it is finished
+pub fn set_progress_bar(pb: Option<ProgressBar>) {
+    *PROGRESS_BAR.write().unwrap() = pb;
+}
+
+//a notice from a request in flight, it would tear the progress bar apart when printed into it
+fn notice(message: String) {
+    match &*PROGRESS_BAR.read().unwrap() {
+        Some(pb) if !pb.is_hidden() => pb.println(message),
+        _ => println!("{}", message),
+    }
+}
+
+//sent unless --user-agent or a User-Agent header says otherwise
+pub const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+//name accepted by --user-agent and the string it stands for
+const USER_AGENT_PRESETS: &[(&str, &str)] = &[
+    (
+        "chrome",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/141.0.0.0 Safari/537.36",
+    ),
+    ("firefox", "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:143.0) Gecko/20100101 Firefox/143.0"),
+    (
+        "safari",
+        "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/18.6 Safari/605.1.15",
+    ),
+    (
+        "edge",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/141.0.0.0 Safari/537.36 Edg/141.0.0.0",
+    ),
+    (
+        "iphone",
+        "Mozilla/5.0 (iPhone; CPU iPhone OS 18_6 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/18.6 Mobile/15E148 Safari/604.1",
+    ),
+    (
+        "android",
+        "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/141.0.0.0 Mobile Safari/537.36",
+    ),
+    ("googlebot", "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)"),
+];
+//presets a rotating user agent picks from, crawlers are left out
+const BROWSER_PRESETS: usize = 6;
+//a proxy failing this many requests in a row is taken out of the rotation for a while
+const PROXY_MAX_FAILURES: u32 = 3;
+const PROXY_COOLDOWN: Duration = Duration::from_secs(60);
+
+//why a request failed without an HTTP response
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorClass {
+    /// The request or the connection attempt timed out
+    Timeout,
+    /// The host name could not be resolved
+    Dns,
+    /// The server refused the connection
+    Refused,
+    /// The connection was reset or closed mid-request
+    Reset,
+    /// TLS handshake or certificate failure
+    Tls,
+    /// Any other failure to connect (unreachable network, ...)
+    Connect,
+    /// Too many redirects or a redirect loop
+    Redirect,
+    /// The response body could not be read or decoded
+    Body,
+    /// Anything else
+    Other,
+}
+
+impl ErrorClass {
+    pub fn classify(error: &reqwest::Error) -> Self {
+        if error.is_timeout() {
+            return ErrorClass::Timeout;
+        }
+        if error.is_redirect() {
+            return ErrorClass::Redirect;
+        }
+
+        //the useful detail sits in the source chain: hyper, the resolver, rustls or an io::Error
+        let mut messages = Vec::new();
+        let mut source = error.source();
+        while let Some(cause) = source {
+            if let Some(io_error) = cause.downcast_ref::<std::io::Error>() {
+                match io_error.kind() {
+                    std::io::ErrorKind::TimedOut => return ErrorClass::Timeout,
+                    std::io::ErrorKind::ConnectionRefused => return ErrorClass::Refused,
+                    std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::UnexpectedEof => return ErrorClass::Reset,
+                    _ => {}
+                }
+            }
+            messages.push(cause.to_string().to_ascii_lowercase());
+            source = cause.source();
+        }
+        let chain = messages.join(" ");
+
+        if chain.contains("dns error") || chain.contains("failed to lookup") || chain.contains("name or service not known") {
+            ErrorClass::Dns
+        } else if chain.contains("certificate") || chain.contains("tls") || chain.contains("ssl") || chain.contains("handshake") {
+            ErrorClass::Tls
+        } else if chain.contains("connection refused") {
+            ErrorClass::Refused
+        } else if chain.contains("connection reset")
+            || chain.contains("connection closed")
+            || chain.contains("broken pipe")
+            || chain.contains("incomplete message")
+        {
+            ErrorClass::Reset
+        } else if error.is_connect() {
+            ErrorClass::Connect
+        } else if error.is_body() || error.is_decode() {
+            ErrorClass::Body
+        } else {
+            ErrorClass::Other
+        }
+    }
+
+    //network trouble that counts towards the adaptive backoff
+    fn is_network(self) -> bool {
+        matches!(
+            self,
+            ErrorClass::Timeout | ErrorClass::Refused | ErrorClass::Reset | ErrorClass::Connect
+        )
+    }
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default();
+        write!(f, "{}", name)
+    }
+}
+
+//which failures are retried, how often and how long to wait in between
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub retries: usize,
+    //wait before the first retry, doubled for each further one
+    pub backoff: Duration,
+    pub errors: Vec<ErrorClass>,
+    //answered with Retry-After when the server sends one
+    pub statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            retries: 3,
+            backoff: BACKOFF_START,
+            errors: vec![ErrorClass::Timeout, ErrorClass::Reset, ErrorClass::Connect],
+            statuses: vec![429, 503],
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn backoff_for(&self, attempt: usize) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1) as u32);
+        self.backoff.saturating_mul(factor).min(BACKOFF_MAX)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum UserAgent {
+    Fixed(String),
+    //a different browser preset for every request
+    Rotate,
+}
+
+//clap value parser for --user-agent: "random", a preset name or a literal user agent
+pub fn parse_user_agent(value: &str) -> Result<UserAgent, String> {
+    if value.eq_ignore_ascii_case("random") {
+        return Ok(UserAgent::Rotate);
+    }
+    let preset = USER_AGENT_PRESETS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+        .map(|(_, user_agent)| *user_agent);
+    let user_agent = preset.unwrap_or(value);
+    HeaderValue::from_str(user_agent).map_err(|_| format!("'{}' is not a valid header value", value))?;
+    Ok(UserAgent::Fixed(user_agent.to_string()))
+}
+
+//clap value parser for -H: "Name: value"
+pub fn parse_header(value: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, header_value) = value
+        .split_once(':')
+        .ok_or_else(|| format!("'{}' is not in the form 'Name: value'", value))?;
+    let name = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|e| format!("invalid header name: {}", e))?;
+    let header_value = HeaderValue::from_str(header_value.trim()).map_err(|e| format!("invalid header value: {}", e))?;
+    Ok((name, header_value))
+}
+
+//"user:password" for --auth-basic
+pub fn basic_auth(credentials: &str) -> Result<HeaderValue, String> {
+    if !credentials.contains(':') {
+        return Err("basic auth credentials must be given as 'user:password'".to_string());
+    }
+    let mut value = HeaderValue::from_str(&format!("Basic {}", STANDARD.encode(credentials))).map_err(|e| e.to_string())?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+pub fn bearer_auth(token: &str) -> Result<HeaderValue, String> {
+    let mut value = HeaderValue::from_str(&format!("Bearer {}", token.trim())).map_err(|e| e.to_string())?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+//clap value parser for --proxy: http, https, socks5 and socks5h (DNS resolved by the proxy) URLs,
+//a bare host:port is taken as an HTTP proxy
+pub fn parse_proxy(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    let proxy = if value.contains("://") { value.to_string() } else { format!("http://{}", value) };
+    let url = Url::parse(&proxy).map_err(|e| format!("invalid proxy URL '{}': {}", value, e))?;
+    if !matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h") {
+        return Err(format!("unsupported proxy scheme '{}', use http, https, socks5 or socks5h", url.scheme()));
+    }
+    if url.host_str().is_none() {
+        return Err(format!("proxy URL '{}' has no host", value));
+    }
+    Ok(proxy)
+}
+
+//one proxy per line, blank lines and #comments are skipped
+pub fn load_proxy_list(path: &std::path::Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(path)?;
+    let mut proxies = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        proxies.push(parse_proxy(line).map_err(|e| format!("{}:{}: {}", path.display(), index + 1, e))?);
+    }
+    Ok(proxies)
+}
+
+//proxy URLs are shown without their credentials
+fn display_proxy(proxy: &str) -> String {
+    match Url::parse(proxy) {
+        Ok(mut url) if !url.username().is_empty() || url.password().is_some() => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            url.to_string().trim_end_matches('/').to_string()
+        }
+        _ => proxy.trim_end_matches('/').to_string(),
+    }
+}
+
+//PEM or DER, Burp exports its CA as DER and ZAP as PEM
+fn load_certificate(path: &std::path::Path) -> Result<Certificate, Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(path)?;
+    let certificate = if bytes.starts_with(b"-----BEGIN") {
+        Certificate::from_pem(&bytes)?
+    } else {
+        Certificate::from_der(&bytes)?
+    };
+    Ok(certificate)
+}
+
+//how requests present themselves: headers, user agent, credentials and cookies
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub timeout: Duration,
+    //sent with every request, a User-Agent given here wins over `user_agent`
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    pub user_agent: UserAgent,
+    pub authorization: Option<HeaderValue>,
+    //the target, credentials are never sent to other hosts (CDNs, allow-listed hosts)
+    pub auth_url: String,
+    //pre-loaded from a cookie file, filled by the login and every Set-Cookie of the scan
+    pub cookies: Arc<Jar>,
+    //requests rotate over these round-robin, empty for direct connections
+    pub proxies: Vec<String>,
+    //extra trusted root, e.g. the CA of an intercepting proxy
+    pub ca_certificate: Option<PathBuf>,
+    //redirects followed per request, 0 hands every 3xx response back as it is
+    pub max_redirects: usize,
+}
+
+//one followed redirect: the URL that answered with `status` and where it pointed to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedirectHop {
+    pub url: String,
+    pub status: u16,
+    pub location: String,
+}
+
+//the redirects followed to get a response, kept in the response's extensions
+#[derive(Debug, Clone, Default)]
+struct RedirectChain(Vec<RedirectHop>);
+
+pub fn redirect_chain(resp: &Response) -> Vec<RedirectHop> {
+    resp.extensions()
+        .get::<RedirectChain>()
+        .map(|chain| chain.0.clone())
+        .unwrap_or_default()
+}
+
+//where a 3xx response points to, resolved against its URL
+pub fn redirect_location(resp: &Response) -> Option<Url> {
+    if !resp.status().is_redirection() {
+        return None;
+    }
+    let location = resp.headers().get(LOCATION)?.to_str().ok()?;
+    resp.url().join(location.trim()).ok()
+}
+
+//where a response was redirected to: the final URL when redirects were followed,
+//the Location of a 3xx response that was handed back as it is
+pub fn redirect_target(resp: &Response) -> Option<String> {
+    match redirect_location(resp) {
+        Some(location) => Some(location.to_string()),
+        None if resp.extensions().get::<RedirectChain>().is_some_and(|chain| !chain.0.is_empty()) => {
+            Some(resp.url().to_string())
+        }
+        None => None,
+    }
+}
+
+//reads at most `limit` bytes of the body, the flag tells whether it was cut off
+pub async fn read_body_limited(mut resp: Response, limit: usize) -> Result<(Vec<u8>, bool), reqwest::Error> {
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        let remaining = limit - body.len();
+        if chunk.len() > remaining {
+            body.extend_from_slice(&chunk[..remaining]);
+            return Ok((body, true));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((body, false))
+}
+
+//a redirect to another host; a switch to https or another port on the same host is not off-site
+pub fn is_off_site(from: &str, to: &str) -> bool {
+    let host = |url: &str| Url::parse(url).ok().and_then(|url| url.host_str().map(|host| host.to_ascii_lowercase()));
+    host(from) != host(to)
+}
+
+//a proxy (or the direct connection) with its own connection pool
+#[derive(Debug)]
+struct Route {
+    client: Client,
+    proxy: Option<String>,
+    health: std::sync::Mutex<RouteHealth>,
+}
+
+#[derive(Debug, Default)]
+struct RouteHealth {
+    failures: u32,
+    down_until: Option<Instant>,
+    //failed the check at startup
+    disabled: bool,
+}
+
+impl Route {
+    fn is_up(&self, now: Instant) -> bool {
+        let health = self.health.lock().unwrap();
+        !health.disabled && health.down_until.is_none_or(|until| now >= until)
+    }
+
+    fn report(&self, failed: bool) {
+        let mut health = self.health.lock().unwrap();
+        if !failed {
+            health.failures = 0;
+            return;
+        }
+        health.failures += 1;
+        if health.failures >= PROXY_MAX_FAILURES
+            && let Some(proxy) = &self.proxy
+        {
+            notice(format!(
+                "Proxy {} failed {} requests in a row, taking it out of the rotation for {}s.",
+                display_proxy(proxy),
+                health.failures,
+                PROXY_COOLDOWN.as_secs()
+            ));
+            health.failures = 0;
+            health.down_until = Some(Instant::now() + PROXY_COOLDOWN);
+        }
+    }
+}
+
+//request pacing, shared by every request to the same host
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThrottleConfig {
+    //requests per second, None for no limit
+    pub rate: Option<f64>,
+    //fixed pause between two requests
+    pub delay: Duration,
+    //random extra pause of up to this much
+    pub jitter: Duration,
+}
+
+#[derive(Debug)]
+struct HostState {
+    tokens: f64,
+    last_refill: Instant,
+    //no request before this point: delay, jitter, backoff and Retry-After all push it out
+    next_allowed: Instant,
+    //true for every failed response (429, 503, transport error) in the window
+    recent: VecDeque<bool>,
+    backoff: Duration,
+}
+
+impl HostState {
+    fn new(burst: f64) -> Self {
+        let now = Instant::now();
+        HostState {
+            tokens: burst,
+            last_refill: now,
+            next_allowed: now,
+            recent: VecDeque::new(),
+            backoff: Duration::ZERO,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Throttle {
+    config: std::sync::Mutex<ThrottleConfig>,
+    hosts: Mutex<HashMap<String, HostState>>,
+}
+
+//a random number in 0..10_000
+fn random_value() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(nanos);
+    hasher.finish() % 10_000
+}
+
+fn random_jitter(max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO;
+    }
+    max.mul_f64(random_value() as f64 / 10_000.0)
+}
+
+//"example.com:443", every scheme default port included so http and https count separately
+fn host_key(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            let host = url.host_str()?.to_ascii_lowercase();
+            Some(format!("{}:{}", host, url.port_or_known_default().unwrap_or(0)))
+        })
+        .unwrap_or_default()
+}
+
+fn retry_after(resp: &Response) -> Option<Duration> {
+    parse_retry_after(resp.headers().get(RETRY_AFTER)?.to_str().ok()?, Utc::now())
+}
+
+//Retry-After is either a number of seconds or an HTTP date; a date that has passed gives None
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - now).to_std().ok()
+}
+
+impl Throttle {
+    //waits until the host's bucket, delay and backoff allow another request
+    async fn acquire(&self, host: &str) {
+        loop {
+            let config = *self.config.lock().unwrap();
+            let burst = config.rate.map(|rate| rate.ceil().max(1.0)).unwrap_or(1.0);
+            let wait = {
+                let mut hosts = self.hosts.lock().await;
+                let state = hosts
+                    .entry(host.to_string())
+                    .or_insert_with(|| HostState::new(burst));
+                let now = Instant::now();
+
+                if let Some(rate) = config.rate {
+                    let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+                    state.tokens = (state.tokens + elapsed * rate).min(burst);
+                    state.last_refill = now;
+                }
+
+                if now < state.next_allowed {
+                    state.next_allowed - now
+                } else if let Some(rate) = config.rate
+                    && state.tokens < 1.0
+                {
+                    Duration::from_secs_f64((1.0 - state.tokens) / rate)
+                } else {
+                    if config.rate.is_some() {
+                        state.tokens -= 1.0;
+                    }
+                    state.next_allowed = now + config.delay + random_jitter(config.jitter) + state.backoff;
+                    return;
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    //feeds the adaptive backoff and honours Retry-After
+    async fn observe(&self, host: &str, failed: bool, pause: Option<Duration>) {
+        let mut hosts = self.hosts.lock().await;
+        let Some(state) = hosts.get_mut(host) else {
+            return;
+        };
+
+        if let Some(pause) = pause {
+            let until = Instant::now() + pause.min(MAX_RETRY_AFTER);
+            state.next_allowed = state.next_allowed.max(until);
+        }
+
+        state.recent.push_back(failed);
+        if state.recent.len() > ERROR_WINDOW {
+            state.recent.pop_front();
+        }
+        if state.recent.len() < ERROR_WINDOW {
+            return;
+        }
+
+        let failures = state.recent.iter().filter(|failed| **failed).count();
+        if failures * 2 >= ERROR_WINDOW {
+            state.backoff = (state.backoff * 2).clamp(BACKOFF_START, BACKOFF_MAX);
+            notice(format!(
+                "High error rate from {} ({} of the last {} requests), slowing down to one request every {:.1}s.",
+                host,
+                failures,
+                ERROR_WINDOW,
+                state.backoff.as_secs_f64()
+            ));
+            //the next adjustment is based on responses at the new pace
+            state.recent.clear();
+        } else if failures * 10 < ERROR_WINDOW && !state.backoff.is_zero() {
+            state.backoff /= 2;
+            if state.backoff < BACKOFF_START / 2 {
+                state.backoff = Duration::ZERO;
+            }
+            state.recent.clear();
+        }
+    }
+}
+
+//A reqwest client whose GET requests are paced per host. Failures and statuses covered
+//by the retry policy are retried, statuses after the server's Retry-After if it sends one.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    routes: Arc<Vec<Route>>,
+    next_route: Arc<AtomicUsize>,
+    throttle: Arc<Throttle>,
+    retry: Arc<RetryPolicy>,
+    //only set when the user agent rotates, a fixed one is a default header
+    rotate_user_agent: bool,
+    authorization: Option<HeaderValue>,
+    auth_host: String,
+    cookies: Arc<Jar>,
+    max_redirects: usize,
+}
+
+impl HttpClient {
+    pub fn new(config: ClientConfig, throttle: ThrottleConfig, retry: RetryPolicy) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            headers.append(name.clone(), value.clone());
+        }
+        let custom_user_agent = headers.contains_key(USER_AGENT);
+        if let UserAgent::Fixed(user_agent) = &config.user_agent
+            && !custom_user_agent
+            && let Ok(value) = HeaderValue::from_str(user_agent)
+        {
+            headers.insert(USER_AGENT, value);
+        }
+
+        let certificate = config.ca_certificate.as_deref().map(load_certificate).transpose()?;
+
+        let proxies: Vec<Option<String>> = if config.proxies.is_empty() {
+            vec![None]
+        } else {
+            config.proxies.iter().cloned().map(Some).collect()
+        };
+        let mut routes = Vec::new();
+        for proxy in proxies {
+            let mut builder = Client::builder()
+                .timeout(config.timeout)
+                .default_headers(headers.clone())
+                .cookie_provider(config.cookies.clone())
+                //redirects are followed by `send`, hop by hop, so the chain can be recorded
+                .redirect(Policy::none());
+            if let Some(certificate) = &certificate {
+                builder = builder.add_root_certificate(certificate.clone());
+            }
+            builder = match &proxy {
+                Some(proxy) => builder.proxy(Proxy::all(proxy)?),
+                //HTTP(S)_PROXY from the environment still apply without --proxy
+                None => builder,
+            };
+            routes.push(Route {
+                client: builder.build()?,
+                proxy,
+                health: std::sync::Mutex::new(RouteHealth::default()),
+            });
+        }
+
+        Ok(HttpClient {
+            routes: Arc::new(routes),
+            next_route: Arc::new(AtomicUsize::new(0)),
+            retry: Arc::new(retry),
+            throttle: Arc::new(Throttle {
+                config: std::sync::Mutex::new(throttle),
+                hosts: Mutex::new(HashMap::new()),
+            }),
+            rotate_user_agent: matches!(config.user_agent, UserAgent::Rotate) && !custom_user_agent,
+            authorization: config.authorization,
+            auth_host: host_key(&config.auth_url),
+            cookies: config.cookies,
+            max_redirects: config.max_redirects,
+        })
+    }
+
+    //number of cookies the jar would send to `url`
+    pub fn cookie_count(&self, url: &Url) -> usize {
+        self.cookies
+            .cookies(url)
+            .and_then(|value| value.to_str().map(|cookies| cookies.split("; ").count()).ok())
+            .unwrap_or(0)
+    }
+
+    //Requests `url` through every proxy and takes the ones that fail out of the rotation for good.
+    //Returns the number of working proxies.
+    pub async fn check_proxies(&self, url: &str) -> usize {
+        let checks = self.routes.iter().map(|route| async move {
+            let started = Instant::now();
+            let result = route.client.get(url).send().await;
+            (route, started.elapsed(), result)
+        });
+        let mut working = 0;
+        for (route, elapsed, result) in futures::future::join_all(checks).await {
+            let proxy = display_proxy(route.proxy.as_deref().unwrap_or_default());
+            match result {
+                Ok(resp) => {
+                    working += 1;
+                    println!("Proxy {}: {} ({} ms)", proxy, resp.status(), elapsed.as_millis());
+                }
+                Err(e) => {
+                    println!("Proxy {}: unusable, {} ({})", proxy, ErrorClass::classify(&e), e);
+                    route.health.lock().unwrap().disabled = true;
+                }
+            }
+        }
+        working
+    }
+
+    //the next proxy that is up in round-robin order, or the next one at all when every proxy is down
+    fn route(&self) -> &Route {
+        let now = Instant::now();
+        let start = self.next_route.fetch_add(1, Ordering::Relaxed);
+        let count = self.routes.len();
+        (0..count)
+            .map(|offset| &self.routes[(start + offset) % count])
+            .find(|route| route.is_up(now))
+            .unwrap_or(&self.routes[start % count])
+    }
+
+    //raises the pause between requests, e.g. to a robots.txt Crawl-delay; never lowers it
+    pub fn raise_delay(&self, delay: Duration) {
+        let mut config = self.throttle.config.lock().unwrap();
+        config.delay = config.delay.max(delay);
+    }
+
+    pub async fn get(&self, url: &str) -> Result<Response, reqwest::Error> {
+        self.send(url, None).await
+    }
+
+    //submits a urlencoded form, used by the login step
+    pub async fn post_form(&self, url: &str, fields: &[(String, String)]) -> Result<Response, reqwest::Error> {
+        self.send(url, Some(fields)).await
+    }
+
+    //Follows up to `max_redirects` redirects like a browser would: 307/308 repeat the request,
+    //the other redirects continue with a GET. The chain ends at the first repeated URL (a loop)
+    //and at a redirect to another host, which is handed back as it is.
+    async fn send(&self, url: &str, form: Option<&[(String, String)]>) -> Result<Response, reqwest::Error> {
+        let mut chain = Vec::new();
+        let mut visited = HashSet::from([url.to_string()]);
+        let mut current = url.to_string();
+        let mut form = form;
+        loop {
+            let mut resp = self.send_with_retries(&current, form).await?;
+            let next = redirect_location(&resp)
+                .filter(|next| chain.len() < self.max_redirects && !is_off_site(url, next.as_str()));
+            let Some(next) = next.filter(|next| visited.insert(next.to_string())) else {
+                resp.extensions_mut().insert(RedirectChain(chain));
+                return Ok(resp);
+            };
+
+            let status = resp.status();
+            chain.push(RedirectHop {
+                url: current,
+                status: status.as_u16(),
+                location: next.to_string(),
+            });
+            if !matches!(status, StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT) {
+                form = None;
+            }
+            current = next.to_string();
+        }
+    }
+
+    //the per-request headers: a rotated user agent, and the credentials for the target host
+    fn prepare(&self, host: &str, mut request: RequestBuilder) -> RequestBuilder {
+        if self.rotate_user_agent {
+            let (_, user_agent) = USER_AGENT_PRESETS[random_value() as usize % BROWSER_PRESETS];
+            request = request.header(USER_AGENT, user_agent);
+        }
+        if let Some(authorization) = &self.authorization
+            && host == self.auth_host
+        {
+            request = request.header(AUTHORIZATION, authorization.clone());
+        }
+        request
+    }
+
+    async fn send_with_retries(&self, url: &str, form: Option<&[(String, String)]>) -> Result<Response, reqwest::Error> {
+        let host = host_key(url);
+        let mut attempt = 0;
+        loop {
+            self.throttle.acquire(&host).await;
+            let route = self.route();
+            let request = match form {
+                Some(fields) => route.client.post(url).form(fields),
+                None => route.client.get(url),
+            };
+            let result = self.prepare(&host, request).send().await;
+
+            let (failed, pause) = match &result {
+                Ok(resp) if self.retry.statuses.contains(&resp.status().as_u16()) => {
+                    (true, Some(retry_after(resp).unwrap_or(self.retry.backoff_for(attempt + 1))))
+                }
+                Ok(_) => (false, None),
+                Err(e) => {
+                    let class = ErrorClass::classify(e);
+                    //with several proxies a network error is worth a try through another one
+                    let retryable =
+                        self.retry.errors.contains(&class) || (self.routes.len() > 1 && class.is_network());
+                    (class.is_network(), retryable.then(|| self.retry.backoff_for(attempt + 1)))
+                }
+            };
+            //a retried request goes out through the next proxy
+            route.report(result.as_ref().is_err_and(|e| ErrorClass::classify(e).is_network()));
+            self.throttle.observe(&host, failed, pause).await;
+
+            let Some(pause) = pause.filter(|_| attempt < self.retry.retries) else {
+                return result;
+            };
+            attempt += 1;
+            let reason = match &result {
+                Ok(resp) => format!("Status {} from {}", resp.status(), url),
+                Err(e) => match &route.proxy {
+                    Some(proxy) => format!("Request to {} via {} failed ({})", url, display_proxy(proxy), ErrorClass::classify(e)),
+                    None => format!("Request to {} failed ({})", url, ErrorClass::classify(e)),
+                },
+            };
+            notice(format!(
+                "{}, retrying after {:.1}s ({}/{}).",
+                reason,
+                pause.min(MAX_RETRY_AFTER).as_secs_f64(),
+                attempt,
+                self.retry.retries
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOST: &str = "example.com:443";
+
+    fn throttle(config: ThrottleConfig) -> Throttle {
+        Throttle {
+            config: std::sync::Mutex::new(config),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn backoff(throttle: &Throttle) -> Duration {
+        throttle.hosts.lock().await[HOST].backoff
+    }
+
+    #[test]
+    fn retry_after_takes_seconds_or_a_date() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2026 07:28:00 GMT").unwrap().with_timezone(&Utc);
+        assert_eq!(parse_retry_after(" 120 ", now), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2026 07:28:30 GMT", now), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2026 07:27:00 GMT", now), None);
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn hosts_are_keyed_with_their_port() {
+        assert_eq!(host_key("https://Example.com/a"), HOST);
+        assert_eq!(host_key("http://example.com/a"), "example.com:80");
+        assert_eq!(host_key("http://example.com:8080/"), "example.com:8080");
+    }
+
+    #[test]
+    fn retry_backoff_doubles_up_to_the_maximum() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff_for(1), BACKOFF_START);
+        assert_eq!(policy.backoff_for(2), BACKOFF_START * 2);
+        assert_eq!(policy.backoff_for(3), BACKOFF_START * 4);
+        assert_eq!(policy.backoff_for(64), BACKOFF_MAX);
+    }
+
+    //serves every connection with `response` and closes it, None answers nothing at all
+    async fn serve(response: Option<&'static [u8]>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0; 4096];
+                    let _ = socket.read(&mut request).await;
+                    match response {
+                        Some(response) => {
+                            let _ = socket.write_all(response).await;
+                        }
+                        None => tokio::time::sleep(Duration::from_secs(5)).await,
+                    }
+                });
+            }
+        });
+        format!("http://{}/", address)
+    }
+
+    async fn failure(client: &Client, url: &str) -> ErrorClass {
+        ErrorClass::classify(&client.get(url).send().await.unwrap_err())
+    }
+
+    #[tokio::test]
+    async fn transport_errors_are_classified_from_the_source_chain() {
+        let client = Client::builder().timeout(Duration::from_millis(200)).build().unwrap();
+
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        assert_eq!(failure(&client, &format!("http://{}/", closed)).await, ErrorClass::Refused);
+        assert_eq!(failure(&client, &serve(None).await).await, ErrorClass::Timeout);
+        assert_eq!(failure(&client, &serve(Some(b"")).await).await, ErrorClass::Reset);
+        assert_eq!(failure(&client, "http://no-such-host.invalid/").await, ErrorClass::Dns);
+        let plain_http = serve(Some(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")).await;
+        assert_eq!(failure(&client, &plain_http.replace("http://", "https://")).await, ErrorClass::Tls);
+        assert_eq!(failure(&client, "http://").await, ErrorClass::Other);
+    }
+
+    #[tokio::test]
+    async fn redirect_and_body_errors_are_classified() {
+        let redirect = serve(Some(b"HTTP/1.1 302 Found\r\nLocation: /again\r\nContent-Length: 0\r\n\r\n")).await;
+        let client = Client::builder().redirect(Policy::limited(2)).build().unwrap();
+        assert_eq!(failure(&client, &redirect).await, ErrorClass::Redirect);
+
+        //a body cut off by the server closing the connection counts as a reset
+        let cut_off = serve(Some(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\ncut off")).await;
+        let error = Client::new().get(&cut_off).send().await.unwrap().text().await.unwrap_err();
+        assert_eq!(ErrorClass::classify(&error), ErrorClass::Reset);
+        let malformed = serve(Some(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nnot a chunk\r\n")).await;
+        let error = Client::new().get(&malformed).send().await.unwrap().text().await.unwrap_err();
+        assert_eq!(ErrorClass::classify(&error), ErrorClass::Body);
+    }
+
+    #[tokio::test]
+    async fn the_rate_allows_a_burst_then_spaces_requests() {
+        let throttle = throttle(ThrottleConfig {
+            rate: Some(20.0),
+            ..ThrottleConfig::default()
+        });
+        let started = Instant::now();
+        for _ in 0..20 {
+            throttle.acquire(HOST).await;
+        }
+        assert!(started.elapsed() < Duration::from_millis(40));
+        throttle.acquire(HOST).await;
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn the_delay_and_retry_after_hold_back_the_next_request() {
+        let throttle = throttle(ThrottleConfig {
+            delay: Duration::from_millis(50),
+            ..ThrottleConfig::default()
+        });
+        throttle.acquire(HOST).await;
+        let started = Instant::now();
+        throttle.acquire(HOST).await;
+        assert!(started.elapsed() >= Duration::from_millis(45));
+
+        throttle.observe(HOST, true, Some(Duration::from_millis(150))).await;
+        let started = Instant::now();
+        throttle.acquire(HOST).await;
+        assert!(started.elapsed() >= Duration::from_millis(140));
+
+        //a day-long Retry-After is capped
+        throttle.observe(HOST, true, Some(Duration::from_secs(86_400))).await;
+        let next_allowed = throttle.hosts.lock().await[HOST].next_allowed;
+        assert!(next_allowed <= Instant::now() + MAX_RETRY_AFTER);
+    }
+
+    #[tokio::test]
+    async fn a_high_error_rate_doubles_the_backoff_and_recovery_relaxes_it() {
+        let throttle = throttle(ThrottleConfig::default());
+        throttle.acquire(HOST).await;
+
+        //a few failures in a full window change nothing
+        for failed in (0..ERROR_WINDOW).map(|i| i % 5 == 0) {
+            throttle.observe(HOST, failed, None).await;
+        }
+        assert_eq!(backoff(&throttle).await, Duration::ZERO);
+
+        for _ in 0..ERROR_WINDOW / 2 {
+            throttle.observe(HOST, true, None).await;
+        }
+        assert_eq!(backoff(&throttle).await, BACKOFF_START);
+        for _ in 0..ERROR_WINDOW {
+            throttle.observe(HOST, true, None).await;
+        }
+        assert_eq!(backoff(&throttle).await, BACKOFF_START * 2);
+
+        for _ in 0..ERROR_WINDOW {
+            throttle.observe(HOST, false, None).await;
+        }
+        assert_eq!(backoff(&throttle).await, BACKOFF_START);
+        for _ in 0..ERROR_WINDOW {
+            throttle.observe(HOST, false, None).await;
+        }
+        assert_eq!(backoff(&throttle).await, BACKOFF_START / 2);
+        for _ in 0..ERROR_WINDOW {
+            throttle.observe(HOST, false, None).await;
+        }
+        assert_eq!(backoff(&throttle).await, Duration::ZERO);
+    }
+}
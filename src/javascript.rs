@@ -0,0 +1,432 @@
+use std::collections::HashSet;
+use std::fs::{self, create_dir_all};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::Instant;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use futures::stream::{self, StreamExt};
+use regex::Regex;
+use scraper::{Html, Selector};
+use serde::Deserialize;
+use url::Url;
+use crate::http::{ErrorClass, HttpClient};
+use crate::report::ProbeRecord;
+use crate::robots::RobotsGroup;
+use crate::scanner::ScannerInfos;
+use crate::state;
+
+pub struct ScriptConfig {
+    //also fetch scripts served from other origins (CDNs, analytics, ...)
+    pub third_party: bool,
+    pub source_maps: bool,
+    pub max_scripts: usize,
+    pub max_size: usize,
+    pub concurrency: usize,
+    pub robots: Option<RobotsGroup>,
+}
+
+//what a script fetch produced, handed to the caller for saving and scanning
+pub enum ScriptFile {
+    Script {
+        url: Url,
+        record: ProbeRecord,
+        body: Option<String>,
+    },
+    //no record for maps inlined as data: URIs, nothing was requested for them
+    SourceMap {
+        url: Url,
+        record: Option<ProbeRecord>,
+        body: Option<String>,
+    },
+}
+
+//the parts of a source map (v3) needed to restore the original files
+#[derive(Debug, Deserialize)]
+pub struct SourceMap {
+    #[serde(default)]
+    pub sources: Vec<String>,
+    #[serde(default, rename = "sourcesContent")]
+    pub sources_content: Vec<Option<String>>,
+}
+
+//MIME type prefixes, "text/html" and friends are not paths
+const MIME_PREFIXES: &[&str] = &["text/", "application/", "image/", "audio/", "video/", "font/", "multipart/"];
+
+//template literals: `/api/users/${id}` becomes /api/users/{id}
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\$\{[^}]*\}").unwrap());
+//relative paths like "api/v1/users" or "./data/config.json"; absolute ones are handled by the resource scanner
+static RELATIVE_PATH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"["'`]((?:\.\.?/)?[A-Za-z0-9_\-]+(?:/[A-Za-z0-9_\-.{}$]+)+/?(?:\?[^"'`\s]*)?)["'`]"#).unwrap()
+});
+static CALL_SITES: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    [
+        //fetch("/api/x"), fetch(`/api/${id}`)
+        r#"\bfetch\(\s*["'`]([^"'`]+)["'`]"#,
+        //axios("/x"), axios.get("/x"), this.$http.post("/x")
+        r#"\b(?:axios|\$http|http|ky)(?:\.(?:get|post|put|patch|delete|head|options|request))?\(\s*["'`]([^"'`]+)["'`]"#,
+        //xhr.open("POST", "/x")
+        r#"\.open\(\s*["'](?:GET|POST|PUT|PATCH|DELETE|HEAD|OPTIONS)["']\s*,\s*["'`]([^"'`]+)["'`]"#,
+        //$.get("/x"), $.ajax({url: "/x"})
+        r#"\$\.(?:get|post|getJSON|ajax)\(\s*(?:\{\s*url\s*:\s*)?["'`]([^"'`]+)["'`]"#,
+        //new WebSocket("wss://...") and EventSource streams
+        r#"new\s+(?:WebSocket|EventSource)\(\s*["'`]([^"'`]+)["'`]"#,
+    ]
+    .iter()
+    .map(|pattern| Regex::new(pattern).unwrap())
+    .collect()
+});
+//query GetUser(...) { ... }, mutation Login { ... }
+static GRAPHQL_OPERATION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(query|mutation|subscription)\s+([A-Za-z_]\w*)\s*[({]").unwrap());
+static ROUTES: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    [
+        //vue-router / angular: { path: "/users/:id", component: ... }
+        r#"\bpath\s*:\s*["'`](/[A-Za-z0-9_\-/:.*{}]*)["'`]"#,
+        //react-router: <Route path="/users/:id" ...>, also compiled to createElement(Route, {path: ...})
+        r#"<Route[^>]+path=["'{]+(/[A-Za-z0-9_\-/:.*]*)"#,
+        //express style: app.get("/users/:id", ...)
+        r#"\b(?:app|router)\.(?:get|post|put|patch|delete|all|use|route)\(\s*["'`](/[A-Za-z0-9_\-/:.*{}]*)["'`]"#,
+    ]
+    .iter()
+    .map(|pattern| Regex::new(pattern).unwrap())
+    .collect()
+});
+static SOURCE_MAPPING_URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[#@]\s*sourceMappingURL=(\S+)").unwrap());
+
+//external scripts and module preloads of a page, resolved against the page URL
+pub fn script_urls(page_url: &Url, html: &str) -> Vec<Url> {
+    let document = Html::parse_document(html);
+    let script_selector = Selector::parse("script[src], link[rel='modulepreload'][href], link[as='script'][href]").unwrap();
+
+    let mut urls = Vec::new();
+    for element in document.select(&script_selector) {
+        let target = element
+            .value()
+            .attr("src")
+            .or_else(|| element.value().attr("href"));
+        if let Some(target) = target
+            && let Ok(mut url) = page_url.join(target.trim())
+            && matches!(url.scheme(), "http" | "https")
+        {
+            url.set_fragment(None);
+            urls.push(url);
+        }
+    }
+    urls
+}
+
+fn record_endpoint(info: &mut ScannerInfos, endpoint: &str) {
+    let endpoint = PLACEHOLDER.replace_all(endpoint.trim(), "{id}");
+    if endpoint.len() < 2 || endpoint.contains(char::is_whitespace) {
+        return;
+    }
+    info.count("api_endpoints", &endpoint);
+    info.api_endpoints.insert(endpoint.into_owned());
+}
+
+//URL literals, fetch/axios/XHR call sites, GraphQL operations and route definitions
+pub fn extract_endpoints(content: &str, info: &mut ScannerInfos) {
+    for cap in RELATIVE_PATH.captures_iter(content) {
+        let path = &cap[1];
+        if !MIME_PREFIXES.iter().any(|prefix| path.starts_with(prefix)) && !path.contains("//") {
+            record_endpoint(info, path);
+        }
+    }
+
+    for re_call in CALL_SITES.iter() {
+        for cap in re_call.captures_iter(content) {
+            record_endpoint(info, &cap[1]);
+        }
+    }
+
+    for cap in GRAPHQL_OPERATION.captures_iter(content) {
+        let operation = format!("GraphQL {} {}", &cap[1], &cap[2]);
+        info.count("api_endpoints", &operation);
+        info.api_endpoints.insert(operation);
+    }
+
+    for re_route in ROUTES.iter() {
+        for cap in re_route.captures_iter(content) {
+            record_endpoint(info, &cap[1]);
+        }
+    }
+}
+
+//the sourceMappingURL comment of a script, or its SourceMap / X-SourceMap header
+fn source_map_reference(body: &str, record: &ProbeRecord) -> Option<String> {
+    if let Some(cap) = SOURCE_MAPPING_URL.captures_iter(body).last() {
+        return Some(cap[1].trim_end_matches("*/").to_string());
+    }
+    record
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("sourcemap") || name.eq_ignore_ascii_case("x-sourcemap"))
+        .map(|(_, value)| value.clone())
+}
+
+//inline maps are embedded as data:application/json;base64,...
+fn decode_data_uri(reference: &str) -> Option<String> {
+    let (_, data) = reference.strip_prefix("data:")?.split_once(";base64,")?;
+    let bytes = BASE64.decode(data).ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+async fn fetch_text(client: &HttpClient, url: Url, max_size: usize) -> (Url, ProbeRecord, Option<String>) {
+    let started = Instant::now();
+    match client.get(url.as_str()).await {
+        Ok(resp) => {
+            let mut record = ProbeRecord::from_response(url.as_str(), &resp, started);
+            if !resp.status().is_success() {
+                println!("Status {}: {}", resp.status(), url);
+                return (url, record, None);
+            }
+            if resp.content_length().is_some_and(|size| size > max_size as u64) {
+                println!("Skipping {}: larger than --max-resource-size", url);
+                return (url, record, None);
+            }
+            match resp.text().await {
+                Ok(text) if text.len() <= max_size => {
+                    record.size = Some(text.len() as u64);
+                    (url, record, Some(text))
+                }
+                Ok(_) => {
+                    println!("Skipping {}: larger than --max-resource-size", url);
+                    (url, record, None)
+                }
+                Err(e) => {
+                    eprintln!("Failed to get text from {}: {}", url, e);
+                    record.set_error(&e);
+                    (url, record, None)
+                }
+            }
+        }
+        Err(e) => {
+            println!("Request failed for {} ({}): {}", url, ErrorClass::classify(&e), e);
+            let record = ProbeRecord::from_error(url.as_str(), &e, started);
+            (url, record, None)
+        }
+    }
+}
+
+fn is_in_scope(url: &Url, origin: &Url, config: &ScriptConfig) -> bool {
+    if !config.third_party && url.origin() != origin.origin() {
+        return false;
+    }
+    //robots.txt only speaks for the target origin
+    url.origin() != origin.origin()
+        || config.robots.as_ref().is_none_or(|group| group.is_allowed(url.path()))
+}
+
+//Fetches the given scripts and, with `source_maps`, the maps they reference (or `<script>.map`
+//when they reference none). Every response is handed to `on_file`; returns the number of scripts fetched.
+pub async fn fetch_scripts<F>(
+    client: &HttpClient,
+    origin: &Url,
+    scripts: Vec<Url>,
+    config: &ScriptConfig,
+    mut on_file: F,
+) -> usize
+where
+    F: FnMut(ScriptFile) -> Result<(), Box<dyn std::error::Error>>,
+{
+    let mut seen = HashSet::new();
+    let queue: Vec<Url> = scripts
+        .into_iter()
+        .filter(|url| is_in_scope(url, origin, config) && seen.insert(url.clone()))
+        .take(config.max_scripts)
+        .collect();
+    println!("Fetching {} linked scripts...", queue.len());
+
+    let mut responses = stream::iter(queue)
+        .map(|url| fetch_text(client, url, config.max_size))
+        .buffered(config.concurrency.max(1));
+
+    let mut scripts_fetched = 0;
+    let mut map_queue = Vec::new();
+    let mut inline_maps = Vec::new();
+    while let Some((url, record, body)) = tokio::select! {
+        script = responses.next() => script,
+        _ = state::wait_for_interrupt() => None,
+    } {
+        if let Some(body) = &body {
+            scripts_fetched += 1;
+            if config.source_maps {
+                match source_map_reference(body, &record) {
+                    Some(reference) if reference.starts_with("data:") => {
+                        if let Some(map) = decode_data_uri(&reference) {
+                            inline_maps.push((url.clone(), map));
+                        }
+                    }
+                    Some(reference) => {
+                        if let Ok(map_url) = url.join(&reference)
+                            && seen.insert(map_url.clone())
+                        {
+                            map_queue.push(map_url);
+                        }
+                    }
+                    None => {
+                        let mut guessed = url.clone();
+                        guessed.set_query(None);
+                        guessed.set_path(&format!("{}.map", url.path()));
+                        if seen.insert(guessed.clone()) {
+                            map_queue.push(guessed);
+                        }
+                    }
+                }
+            }
+        }
+        if let Err(e) = on_file(ScriptFile::Script { url: url.clone(), record, body }) {
+            eprintln!("Error processing {}: {}", url, e);
+        }
+    }
+    drop(responses);
+
+    //inline maps have no URL of their own, they are reported as "<script>.map"
+    for (script_url, map) in inline_maps {
+        let mut map_url = script_url.clone();
+        map_url.set_path(&format!("{}.map", script_url.path()));
+        if let Err(e) = on_file(ScriptFile::SourceMap { url: map_url.clone(), record: None, body: Some(map) }) {
+            eprintln!("Error processing {}: {}", map_url, e);
+        }
+    }
+
+    if !map_queue.is_empty() && !state::interrupted() {
+        println!("Fetching {} source maps...", map_queue.len());
+        let mut maps = stream::iter(map_queue)
+            .map(|url| fetch_text(client, url, config.max_size))
+            .buffered(config.concurrency.max(1));
+        while let Some((url, record, body)) = tokio::select! {
+            map = maps.next() => map,
+            _ = state::wait_for_interrupt() => None,
+        } {
+            if let Err(e) = on_file(ScriptFile::SourceMap { url: url.clone(), record: Some(record), body }) {
+                eprintln!("Error processing {}: {}", url, e);
+            }
+        }
+    }
+
+    scripts_fetched
+}
+
+//"webpack:///./src/api/client.ts" -> "src/api/client.ts", without any way out of the target directory
+fn original_source_path(source: &str) -> PathBuf {
+    let source = source.split_once("://").map(|(_, rest)| rest).unwrap_or(source);
+    let source = source.split(['?', '#']).next().unwrap_or("");
+    let mut path = PathBuf::new();
+    for component in source.split(['/', '\\']) {
+        if component.is_empty() || component == "." || component == ".." {
+            continue;
+        }
+        path.push(component.replace([':', '*', '?', '"', '|', '<', '>', ' '], "_"));
+    }
+    if path.as_os_str().is_empty() {
+        path.push("unnamed");
+    }
+    path
+}
+
+//Writes every source with embedded content into `dir`, keeping the original directory layout.
+//Returns (source name, written file) pairs.
+pub fn unpack_source_map(map: &str, dir: &Path) -> Result<Vec<(String, PathBuf)>, Box<dyn std::error::Error>> {
+    let map: SourceMap = serde_json::from_str(map)?;
+    let mut written = Vec::new();
+    for (source, content) in map.sources.iter().zip(map.sources_content.iter()) {
+        let Some(content) = content else {
+            continue;
+        };
+        //bundler runtime and third-party packages, nothing of the application in there
+        let relative = original_source_path(source);
+        if relative.starts_with("webpack") || relative.iter().any(|component| component == "node_modules") {
+            continue;
+        }
+        let path = dir.join(relative);
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        fs::write(&path, content)?;
+        written.push((source.clone(), path));
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_paths_cannot_leave_the_target_directory() {
+        assert_eq!(original_source_path("../../../etc/cron.d/job"), PathBuf::from("etc/cron.d/job"));
+        assert_eq!(original_source_path("src/../../app.js"), PathBuf::from("src/app.js"));
+        assert_eq!(original_source_path("/etc/passwd"), PathBuf::from("etc/passwd"));
+        assert_eq!(original_source_path("..\\..\\Windows\\win.ini"), PathBuf::from("Windows/win.ini"));
+        assert_eq!(original_source_path("C:\\Users\\dev\\app.js"), PathBuf::from("C_/Users/dev/app.js"));
+        assert_eq!(original_source_path("../.."), PathBuf::from("unnamed"));
+        for source in ["../../etc/passwd", "/etc/passwd", "..\\..\\x", "C:\\x", "webpack:///../../x"] {
+            assert!(original_source_path(source).is_relative());
+            assert!(!original_source_path(source).components().any(|c| c == std::path::Component::ParentDir));
+        }
+    }
+
+    #[test]
+    fn bundler_prefixes_and_queries_are_dropped() {
+        assert_eq!(original_source_path("webpack:///./src/api/client.ts"), PathBuf::from("src/api/client.ts"));
+        assert_eq!(original_source_path("webpack://app/src/App.vue?5a3b"), PathBuf::from("app/src/App.vue"));
+        assert_eq!(original_source_path("webpack:///webpack/bootstrap"), PathBuf::from("webpack/bootstrap"));
+    }
+
+    #[test]
+    fn unpacking_skips_bundler_runtime_and_packages() {
+        let dir = std::env::temp_dir().join(format!("sourcemap-test-{}", std::process::id()));
+        let map = r#"{
+            "sources": ["webpack:///webpack/bootstrap", "webpack:///./node_modules/lib/index.js", "webpack:///../../../escape.js", "webpack:///./src/main.js", "webpack:///./src/empty.js"],
+            "sourcesContent": ["runtime", "library", "outside", "const key = 1;", null]
+        }"#;
+        let written = unpack_source_map(map, &dir).unwrap();
+        let files: Vec<&Path> = written.iter().map(|(_, path)| path.as_path()).collect();
+        assert_eq!(files, [dir.join("escape.js"), dir.join("src/main.js")]);
+        assert_eq!(fs::read_to_string(dir.join("src/main.js")).unwrap(), "const key = 1;");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn endpoints_come_from_call_sites_routes_and_graphql() {
+        let script = r#"
+            fetch(`/api/users/${user.id}`);
+            axios.post("/api/login", data);
+            xhr.open("GET", "/legacy/status.php");
+            const routes = [{ path: "/admin/:section", component: Admin }];
+            const q = gql`query GetOrders($first: Int) { orders { id } }`;
+            const type = "application/json";
+            const base = "api/v2/items";
+        "#;
+        let mut info = ScannerInfos::default();
+        extract_endpoints(script, &mut info);
+        for endpoint in ["/api/users/{id}", "/api/login", "/legacy/status.php", "/admin/:section", "GraphQL query GetOrders", "api/v2/items"] {
+            assert!(info.api_endpoints.contains(endpoint), "{} missing", endpoint);
+        }
+        assert!(!info.api_endpoints.contains("application/json"));
+    }
+
+    #[test]
+    fn source_maps_are_found_in_comments_and_headers() {
+        let record = ProbeRecord {
+            url: "https://example.com/app.js".to_string(),
+            status: Some(200),
+            content_type: None,
+            size: None,
+            redirect_target: None,
+            redirect_chain: Vec::new(),
+            off_site_redirect: false,
+            elapsed_ms: 0,
+            error: None,
+            error_class: None,
+            soft_404: false,
+            filtered: false,
+            headers: vec![("SourceMap".to_string(), "/maps/app.js.map".to_string())],
+        };
+        assert_eq!(source_map_reference("var a;\n//# sourceMappingURL=app.js.map", &record).as_deref(), Some("app.js.map"));
+        assert_eq!(source_map_reference("var a;", &record).as_deref(), Some("/maps/app.js.map"));
+        assert_eq!(decode_data_uri("data:application/json;base64,eyJ2ZXJzaW9uIjozfQ==").as_deref(), Some(r#"{"version":3}"#));
+    }
+}
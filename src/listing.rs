@@ -0,0 +1,368 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::sync::LazyLock;
+use std::time::Instant;
+use futures::stream::{self, StreamExt};
+use regex::Regex;
+use url::Url;
+use crate::http::{self, ErrorClass, HttpClient};
+use crate::report::ProbeRecord;
+use crate::robots::RobotsGroup;
+use crate::state;
+
+//title and heading texts of the autoindex pages of Apache, nginx, lighttpd, IIS and Python's http.server
+const LISTING_MARKERS: &[&str] = &[
+    "<title>Index of /",
+    "<h1>Index of /",
+    "Directory listing for /",
+    "[To Parent Directory]",
+];
+
+static ANCHOR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?is)<a\s[^>]*?href\s*=\s*["']([^"']+)["'][^>]*>(.*?)</a>"#).unwrap());
+//entries are one per line (Apache, nginx, lighttpd, Python) or separated by <br> (IIS)
+static ENTRY_SEPARATOR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\n|<br\s*/?>").unwrap());
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+//2024-01-31 10:00, 31-Jan-2024 10:00, 2024-Jan-31 10:00:00, 1/31/2024 10:00 AM, Wednesday, January 31, 2024 10:00 AM
+static DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"\d{4}-\d{2}-\d{2}[ T]\d{2}:\d{2}(?::\d{2})?",
+        r"|\d{1,2}-[A-Za-z]{3}-\d{4} \d{2}:\d{2}(?::\d{2})?",
+        r"|\d{4}-[A-Za-z]{3}-\d{2} \d{2}:\d{2}(?::\d{2})?",
+        r"|\d{1,2}/\d{1,2}/\d{4}\s+\d{1,2}:\d{2}(?:\s*[AP]M)?",
+        r"|[A-Z][a-z]+day, [A-Z][a-z]+ \d{1,2}, \d{4}\s+\d{1,2}:\d{2}(?:\s*[AP]M)?",
+    ))
+    .unwrap()
+});
+static SIZE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\d+(?:\.\d+)?[KMGTkmgt]?$").unwrap());
+static LISTED_DIRECTORY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(?:Index of|Directory listing for)\s+(/[^<]*)").unwrap());
+
+pub fn is_directory_listing(html: &str) -> bool {
+    LISTING_MARKERS.iter().any(|marker| html.contains(marker))
+}
+
+//a file or directory shown on a listing page, size and date as the server printed them
+#[derive(Debug, Clone)]
+pub struct ListingEntry {
+    pub href: String,
+    pub name: String,
+    pub directory: bool,
+    pub size: Option<String>,
+    pub modified: Option<String>,
+}
+
+impl ListingEntry {
+    //the listed size in bytes, "12K" counts as 12 KiB
+    pub fn size_in_bytes(&self) -> Option<u64> {
+        let size = self.size.as_deref()?;
+        let (number, unit) = match size.chars().last() {
+            Some(unit) if unit.is_ascii_alphabetic() => (&size[..size.len() - 1], unit.to_ascii_uppercase()),
+            _ => (size, 'B'),
+        };
+        let factor: u64 = match unit {
+            'K' => 1 << 10,
+            'M' => 1 << 20,
+            'G' => 1 << 30,
+            'T' => 1 << 40,
+            _ => 1,
+        };
+        number.parse::<f64>().ok().map(|number| (number * factor as f64) as u64)
+    }
+
+    //"directory", size and date, whichever the listing shows
+    fn details(&self) -> Vec<&str> {
+        [self.directory.then_some("directory"), self.size.as_deref(), self.modified.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    //"/backup/db.sql (12K, 2024-01-31 10:00)", relative entries are put below `directory`
+    pub fn describe(&self, directory: Option<&str>) -> String {
+        let location = match directory {
+            Some(directory) if !self.href.starts_with('/') => format!("{}/{}", directory.trim_end_matches('/'), self.href),
+            _ => self.href.clone(),
+        };
+        match self.details() {
+            details if details.is_empty() => location,
+            details => format!("{} ({})", location, details.join(", ")),
+        }
+    }
+}
+
+//the entry as the listing names it, "db.sql (12K, 2024-01-31 10:00)"
+impl fmt::Display for ListingEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.details() {
+            details if details.is_empty() => write!(f, "{}", self.name),
+            details => write!(f, "{} ({})", self.name, details.join(", ")),
+        }
+    }
+}
+
+fn strip_tags(html: &str) -> String {
+    TAG.replace_all(html, " ")
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+//sort links, the parent directory and links leaving the listing are not entries
+fn is_entry(href: &str, text: &str) -> bool {
+    let text = text.to_ascii_lowercase();
+    !(href.starts_with('?')
+        || href.starts_with('#')
+        || href.starts_with("..")
+        || href == "/"
+        || href.contains(':')
+        || text.contains("parent directory"))
+}
+
+//the directory a listing page shows, from "Index of /backup/"
+pub fn listed_directory(html: &str) -> Option<String> {
+    LISTED_DIRECTORY
+        .captures(html)
+        .map(|captures| captures[1].trim().to_string())
+}
+
+//The entries of an autoindex page. Each entry is the link plus the text around it on its
+//line, which holds the date and size on every common server, before the link on IIS.
+pub fn parse_listing(html: &str) -> Vec<ListingEntry> {
+    let mut entries = Vec::new();
+    for segment in ENTRY_SEPARATOR.split(html) {
+        let anchors: Vec<_> = ANCHOR
+            .captures_iter(segment)
+            .filter(|captures| is_entry(&captures[1], &strip_tags(&captures[2])))
+            .collect();
+        for (index, captures) in anchors.iter().enumerate() {
+            let anchor = captures.get(0).unwrap();
+            //with several entries on one line, only the text up to the next one belongs to this entry
+            let details = if anchors.len() == 1 {
+                format!("{} {}", &segment[..anchor.start()], &segment[anchor.end()..])
+            } else {
+                let end = anchors.get(index + 1).map_or(segment.len(), |next| next.get(0).unwrap().start());
+                segment[anchor.end()..end].to_string()
+            };
+            let details = strip_tags(&details);
+            let modified = DATE.find(&details).map(|date| date.as_str().split_whitespace().collect::<Vec<_>>().join(" "));
+            let rest = DATE.replace(&details, " ");
+            let size = rest.split_whitespace().rfind(|token| SIZE.is_match(token)).map(String::from);
+
+            let href = captures[1].trim().to_string();
+            let text = strip_tags(&captures[2]).trim().to_string();
+            let directory = href.ends_with('/') || details.contains("<dir>");
+            //nginx cuts long names short with "..>"
+            let name = if text.is_empty() || text.ends_with("..>") {
+                href.trim_end_matches('/').rsplit('/').next().unwrap_or_default().to_string()
+            } else {
+                text
+            };
+            entries.push(ListingEntry {
+                href,
+                name,
+                directory,
+                size: if directory { None } else { size },
+                modified,
+            });
+        }
+    }
+    entries
+}
+
+pub struct HarvestConfig {
+    //files and subdirectories downloaded from all listings together
+    pub max_files: usize,
+    pub max_size: usize,
+    pub concurrency: usize,
+    pub robots: Option<RobotsGroup>,
+}
+
+//a downloaded listing entry, handed to the caller for saving and scanning
+pub struct HarvestedFile {
+    pub url: Url,
+    pub record: ProbeRecord,
+    pub content_type: String,
+    pub body: Option<Vec<u8>>,
+}
+
+async fn fetch_file(client: &HttpClient, url: Url, max_size: usize) -> HarvestedFile {
+    let started = Instant::now();
+    let resp = match client.get(url.as_str()).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            println!("Request failed for {} ({}): {}", url, ErrorClass::classify(&e), e);
+            let record = ProbeRecord::from_error(url.as_str(), &e, started);
+            return HarvestedFile { url, record, content_type: String::new(), body: None };
+        }
+    };
+    let mut record = ProbeRecord::from_response(url.as_str(), &resp, started);
+    let content_type = record.content_type.clone().unwrap_or_default();
+    if !resp.status().is_success() {
+        println!("Status {}: {}", resp.status(), url);
+        return HarvestedFile { url, record, content_type, body: None };
+    }
+    if resp.content_length().is_some_and(|size| size > max_size as u64) {
+        println!("Skipping {}: larger than --harvest-max-size", url);
+        return HarvestedFile { url, record, content_type, body: None };
+    }
+    let body = match http::read_body_limited(resp, max_size).await {
+        Ok((body, false)) => {
+            record.size = Some(body.len() as u64);
+            Some(body)
+        }
+        Ok((_, true)) => {
+            println!("Skipping {}: larger than --harvest-max-size", url);
+            None
+        }
+        Err(e) => {
+            eprintln!("Failed to download {}: {}", url, e);
+            record.set_error(&e);
+            None
+        }
+    };
+    HarvestedFile { url, record, content_type, body }
+}
+
+//Downloads the files listed on the given listing pages and walks into listed subdirectories,
+//breadth first, never above the listing it started from. Files larger than `max_size` (by the
+//listed size or the response) are skipped. Every download is handed to `on_file`; returns the
+//number of files saved.
+pub async fn harvest<F>(client: &HttpClient, listings: Vec<(Url, String)>, config: &HarvestConfig, mut on_file: F) -> usize
+where
+    F: FnMut(HarvestedFile) -> Result<(), Box<dyn std::error::Error>>,
+{
+    let mut seen: HashSet<Url> = listings.iter().map(|(url, _)| url.clone()).collect();
+    let mut pages = VecDeque::from(listings);
+    let mut downloads = 0;
+    let mut files_saved = 0;
+
+    while let Some((page_url, html)) = pages.pop_front() {
+        let mut queue = Vec::new();
+        for entry in parse_listing(&html) {
+            let Ok(url) = page_url.join(&entry.href) else {
+                continue;
+            };
+            let below = url.origin() == page_url.origin()
+                && url.path().starts_with(page_url.path())
+                && url.path() != page_url.path();
+            if !below || config.robots.as_ref().is_some_and(|group| !group.is_allowed(url.path())) {
+                continue;
+            }
+            if entry.size_in_bytes().is_some_and(|size| size > config.max_size as u64) {
+                println!("Skipping {} in {}: larger than --harvest-max-size", entry, page_url);
+                continue;
+            }
+            if seen.insert(url.clone()) {
+                queue.push(url);
+            }
+        }
+
+        let remaining = config.max_files.saturating_sub(downloads);
+        if queue.len() > remaining {
+            println!(
+                "Harvest limit of {} downloads reached, {} entries of {} are left out.",
+                config.max_files,
+                queue.len() - remaining,
+                page_url
+            );
+            queue.truncate(remaining);
+        }
+        if queue.is_empty() {
+            continue;
+        }
+        downloads += queue.len();
+        println!("Harvesting {} entries of {}...", queue.len(), page_url);
+
+        let mut responses = stream::iter(queue)
+            .map(|url| fetch_file(client, url, config.max_size))
+            .buffered(config.concurrency.max(1));
+        while let Some(file) = tokio::select! {
+            file = responses.next() => file,
+            _ = state::wait_for_interrupt() => None,
+        } {
+            //a subdirectory that is listed as well is harvested in turn
+            if let Some(body) = &file.body {
+                files_saved += 1;
+                let text = String::from_utf8_lossy(body);
+                if file.content_type.contains("text/html") && is_directory_listing(&text) {
+                    pages.push_back((file.url.clone(), text.into_owned()));
+                }
+            }
+            let url = file.url.clone();
+            if let Err(e) = on_file(file) {
+                eprintln!("Error processing {}: {}", url, e);
+            }
+        }
+        if state::interrupted() {
+            break;
+        }
+    }
+    files_saved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APACHE: &str = r#"<html><head><title>Index of /files</title></head><body><h1>Index of /files</h1><table>
+<tr><th><a href="?C=N;O=D">Name</a></th><th><a href="?C=M;O=A">Last modified</a></th><th><a href="?C=S;O=A">Size</a></th></tr>
+<tr><td><img src="/icons/back.gif" alt="[PARENTDIR]"></td><td><a href="/">Parent Directory</a></td><td>&nbsp;</td><td align="right">  - </td></tr>
+<tr><td><img src="/icons/unknown.gif" alt="[   ]"></td><td><a href="db.sql">db.sql</a></td><td align="right">2024-01-31 10:00  </td><td align="right"> 12K</td></tr>
+<tr><td><img src="/icons/folder.gif" alt="[DIR]"></td><td><a href="old/">old/</a></td><td align="right">2023-12-01 08:15  </td><td align="right">  - </td></tr>
+</table></body></html>"#;
+
+    const NGINX: &str = r#"<html><head><title>Index of /dl/</title></head><body><h1>Index of /dl/</h1><hr><pre><a href="../">../</a>
+<a href="images/">images/</a>                                            31-Jan-2024 10:00                   -
+<a href="a-very-long-file-name-that-nginx-cuts-short.tar.gz">a-very-long-file-name-that-nginx-cuts-..&gt;</a> 02-Feb-2024 11:22              1048576
+</pre><hr></body></html>"#;
+
+    const IIS: &str = r#"<html><head><title>example.com - /share/</title></head><body><H1>example.com - /share/</H1><hr><pre><A HREF="/">[To Parent Directory]</A><br><br> 1/31/2024 10:00 AM        &lt;dir&gt; <A HREF="/share/sub/">sub</A><br> 2/15/2024  3:04 PM        12345 <A HREF="/share/web.config.bak">web.config.bak</A><br></pre><hr></body></html>"#;
+
+    const PYTHON: &str = r#"<html><head><title>Directory listing for /backup/</title></head><body><h1>Directory listing for /backup/</h1><hr><ul>
+<li><a href=".env">.env</a></li>
+<li><a href="old/">old/</a></li>
+</ul><hr></body></html>"#;
+
+    fn described(html: &str) -> Vec<String> {
+        let directory = listed_directory(html);
+        parse_listing(html).iter().map(|entry| entry.describe(directory.as_deref())).collect()
+    }
+
+    #[test]
+    fn listings_of_common_servers_are_recognized() {
+        for html in [APACHE, NGINX, IIS, PYTHON] {
+            assert!(is_directory_listing(html));
+        }
+        assert!(!is_directory_listing("<html><title>Welcome</title><a href=\"/about\">About</a></html>"));
+    }
+
+    #[test]
+    fn apache_entries_have_size_and_date() {
+        assert_eq!(described(APACHE), ["/files/db.sql (12K, 2024-01-31 10:00)", "/files/old/ (directory, 2023-12-01 08:15)"]);
+        assert_eq!(parse_listing(APACHE)[0].size_in_bytes(), Some(12 * 1024));
+    }
+
+    #[test]
+    fn nginx_names_cut_short_are_taken_from_the_link() {
+        let entries = parse_listing(NGINX);
+        assert_eq!(entries[1].name, "a-very-long-file-name-that-nginx-cuts-short.tar.gz");
+        assert_eq!(entries[1].size_in_bytes(), Some(1048576));
+        assert_eq!(described(NGINX)[0], "/dl/images/ (directory, 31-Jan-2024 10:00)");
+    }
+
+    #[test]
+    fn iis_details_come_before_the_link() {
+        assert_eq!(
+            described(IIS),
+            ["/share/sub/ (directory, 1/31/2024 10:00 AM)", "/share/web.config.bak (12345, 2/15/2024 3:04 PM)"]
+        );
+    }
+
+    #[test]
+    fn python_listings_only_name_their_entries() {
+        assert_eq!(described(PYTHON), ["/backup/.env", "/backup/old/ (directory)"]);
+        assert_eq!(parse_listing(PYTHON)[0].to_string(), ".env");
+    }
+}
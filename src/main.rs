@@ -1,16 +1,89 @@
+mod crawler;
+mod filters;
+mod headers;
+mod http;
+mod javascript;
+mod listing;
+mod output;
+mod recursion;
+mod report;
+mod resources;
+mod robots;
 mod scanner;
+mod secrets;
+mod session;
+mod sitemap;
+mod soft404;
+mod state;
+mod technologies;
+mod wordlist;
 
 use clap::Parser;
-use reqwest::blocking::Client;
-use std::fs::{self, File, create_dir_all};
-use std::io::{self, Write, BufRead, BufReader};
-use std::path::Path;
-use std::time::Duration;
+use futures::stream::{self, StreamExt};
+use reqwest::cookie::Jar;
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use http::{ClientConfig, ErrorClass, HttpClient, RetryPolicy, ThrottleConfig, UserAgent};
+use std::fs::File;
+use std::io::{self, Write, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use chrono::Utc;
+use url::Url;
 use indicatif::{ProgressBar, ProgressStyle};
+use output::OutputPaths;
+use filters::ResponseFilter;
+use regex::Regex;
+use report::{ProbeRecord, Report};
+use soft404::{ResponseFingerprint, Soft404Detector, Soft404Mode};
+use state::{PathProgress, ProgressLog, ScanState};
 
-struct Data {
-    data: Vec<u8>,
-    html_content: String,
+//outcome of a single probe, handed back from the worker pool to the scan loop
+enum ProbeOutcome {
+    Html(String),
+    Resource {
+        content_type: String,
+        body: Vec<u8>,
+        truncated: bool,
+    },
+    Oversized {
+        content_type: String,
+        size: u64,
+    },
+    //a 3xx that was not followed, with its body
+    Redirect(String),
+    //a status outside --match-codes, printed but not kept
+    Status(StatusCode),
+    //dropped by --filter-codes, --filter-size, --filter-words, --filter-regex or --match-regex
+    Filtered,
+    TextError(reqwest::Error),
+    RequestError(reqwest::Error),
+}
+
+impl ProbeOutcome {
+    //the outcome as recorded in the progress log
+    fn name(&self) -> &'static str {
+        match self {
+            ProbeOutcome::Html(_) => "html",
+            ProbeOutcome::Resource { .. } => "resource",
+            ProbeOutcome::Oversized { .. } => "oversized",
+            ProbeOutcome::Redirect(_) => "redirect",
+            ProbeOutcome::Status(_) => "status",
+            ProbeOutcome::Filtered => "filtered",
+            ProbeOutcome::TextError(_) | ProbeOutcome::RequestError(_) => "error",
+        }
+    }
+}
+
+struct ProbeResult {
+    path: String,
+    url: String,
+    record: ProbeRecord,
+    outcome: ProbeOutcome,
 }
 
 #[derive(Parser, Debug)]
@@ -20,14 +93,255 @@ struct Args {
     #[arg()]
     url: String,
 
-    /// Path to the wordlist file
-    #[arg(short = 'd', long = "wordlist")]
-    wordlist: String,
-}
+    /// Path to a wordlist file, FILE:KEYWORD names its placeholder; several lists are combined
+    /// through placeholders in the URL, e.g. https://example.com/{FUZZ}/{FUZZ2} (repeatable)
+    #[arg(short = 'd', long = "wordlist", required = true)]
+    wordlists: Vec<String>,
+
+    /// Extensions appended to every word of the first wordlist, e.g. php,bak,old,zip
+    #[arg(short = 'x', long = "extensions", value_delimiter = ',', value_parser = wordlist::parse_extension)]
+    extensions: Vec<String>,
+
+    /// Also try backup and editor copies of every word (admin.php~, admin.php.bak, .admin.php.swp, ...)
+    #[arg(long = "backups")]
+    backups: bool,
+
+    /// Also try the lowercase, uppercase and capitalized form of every word
+    #[arg(long = "case-variants")]
+    case_variants: bool,
+
+    /// Brute-force directories found during the scan with the wordlist as well
+    #[arg(long = "recursive")]
+    recursive: bool,
+
+    /// How many directory levels below the target are brute-forced
+    #[arg(long = "recursion-depth", default_value_t = 3, requires = "recursive")]
+    recursion_depth: usize,
+
+    /// Maximum number of directories queued from the hits below one directory
+    #[arg(long = "max-branch-dirs", default_value_t = 25, requires = "recursive")]
+    max_branch_dirs: usize,
+
+    /// Hits after which a directory is abandoned, as it probably answers every path
+    #[arg(long = "max-branch-hits", default_value_t = 200, requires = "recursive")]
+    max_branch_hits: usize,
+
+    /// Directories matching this regular expression are not entered, e.g. '^/(css|js|images)/' (repeatable)
+    #[arg(long = "recursion-exclude", value_parser = Regex::new, requires = "recursive")]
+    recursion_exclude: Vec<Regex>,
+
+    /// Download the files shown on directory listing pages, walking into listed subdirectories
+    #[arg(long = "harvest-listings")]
+    harvest_listings: bool,
+
+    /// Maximum number of files and subdirectories downloaded from directory listings
+    #[arg(long = "harvest-max-files", default_value_t = 200, requires = "harvest_listings")]
+    harvest_max_files: usize,
+
+    /// Maximum size in bytes of a file downloaded from a directory listing, larger ones are skipped
+    #[arg(long = "harvest-max-size", default_value_t = 10 * 1024 * 1024, requires = "harvest_listings")]
+    harvest_max_size: usize,
+
+    //the path below the URL with the wordlist placeholders, split off the URL in main
+    #[arg(skip)]
+    path_template: Option<String>,
+
+    /// Number of paths probed in parallel
+    #[arg(short = 'c', long = "concurrency", default_value_t = 10)]
+    concurrency: usize,
+
+    /// Request timeout in seconds
+    #[arg(short = 't', long = "timeout", default_value_t = 10)]
+    timeout: u64,
+
+    /// Extra request header as 'Name: value' (repeatable)
+    #[arg(short = 'H', long = "header", value_parser = http::parse_header)]
+    headers: Vec<(HeaderName, HeaderValue)>,
+
+    /// User agent: a literal string, a preset (chrome, firefox, safari, edge, iphone, android, googlebot) or 'random'
+    #[arg(short = 'A', long = "user-agent", value_parser = http::parse_user_agent, default_value = http::DEFAULT_USER_AGENT)]
+    user_agent: UserAgent,
+
+    /// Netscape cookie file (as written by curl -c or browser export add-ons) whose cookies are sent
+    #[arg(long = "cookies")]
+    cookies: Option<PathBuf>,
+
+    /// HTTP basic auth credentials as user:password, sent to the target host only
+    #[arg(long = "auth-basic", value_parser = http::basic_auth, conflicts_with = "auth_bearer")]
+    auth_basic: Option<HeaderValue>,
+
+    /// Bearer token sent in the Authorization header to the target host only
+    #[arg(long = "auth-bearer", value_parser = http::bearer_auth)]
+    auth_bearer: Option<HeaderValue>,
+
+    /// Page with a login form, submitted before the scan; the session cookies are kept
+    #[arg(long = "login-url", requires = "login_data")]
+    login_url: Option<String>,
+
+    /// Form fields for the login, urlencoded: 'user=admin&password=secret'
+    #[arg(long = "login-data", requires = "login_url")]
+    login_data: Option<String>,
+
+    /// Proxy for all requests: http://, https://, socks5:// or socks5h:// (repeatable, rotated round-robin)
+    #[arg(long = "proxy", value_parser = http::parse_proxy)]
+    proxies: Vec<String>,
+
+    /// File with one proxy URL per line, added to the --proxy rotation
+    #[arg(long = "proxy-list")]
+    proxy_list: Option<PathBuf>,
+
+    /// CA certificate (PEM or DER) to trust, e.g. the one of an intercepting proxy like Burp or ZAP
+    #[arg(long = "proxy-ca")]
+    proxy_ca: Option<PathBuf>,
+
+    /// Maximum number of redirects followed per request
+    #[arg(long = "max-redirects", default_value_t = 10)]
+    max_redirects: usize,
+
+    /// Report 3xx responses without following them
+    #[arg(long = "no-follow-redirects")]
+    no_follow_redirects: bool,
+
+    /// Maximum requests per second to each host
+    #[arg(long = "rate")]
+    rate: Option<f64>,
+
+    /// Pause in milliseconds between two requests to the same host
+    #[arg(long = "delay", default_value_t = 0)]
+    delay: u64,
+
+    /// Random extra pause of up to this many milliseconds, added to --delay
+    #[arg(long = "jitter", default_value_t = 0)]
+    jitter: u64,
+
+    /// How often a failed request is retried
+    #[arg(long = "retries", default_value_t = 3)]
+    retries: usize,
+
+    /// Wait in milliseconds before the first retry, doubled for each further one
+    #[arg(long = "retry-backoff", default_value_t = 500)]
+    retry_backoff: u64,
+
+    /// Network errors that are retried (comma-separated)
+    #[arg(long = "retry-on", value_enum, value_delimiter = ',', default_values_t = [ErrorClass::Timeout, ErrorClass::Reset, ErrorClass::Connect])]
+    retry_on: Vec<ErrorClass>,
+
+    /// HTTP statuses that are retried, after Retry-After when the server sends one (comma-separated)
+    #[arg(long = "retry-status", value_delimiter = ',', default_values_t = [429, 503])]
+    retry_statuses: Vec<u16>,
 
-//constants for the output and intel directory
-const OUTPUT_DIR: &str = "found_html";
-const INTEL_DIR_DISPLAY: &str = "intel";
+    /// Follow same-origin links found on fetched pages
+    #[arg(long = "crawl")]
+    crawl: bool,
+
+    /// Maximum link depth followed by the crawler
+    #[arg(long = "max-depth", default_value_t = 3)]
+    max_depth: usize,
+
+    /// Maximum number of pages fetched by the crawler
+    #[arg(long = "max-pages", default_value_t = 500)]
+    max_pages: usize,
+
+    /// Additional host the crawler may follow links to (repeatable)
+    #[arg(long = "allow-host")]
+    allow_hosts: Vec<String>,
+
+    /// Download linked scripts and extract API endpoints from them
+    #[arg(long = "fetch-scripts")]
+    fetch_scripts: bool,
+
+    /// Also download scripts served from other origins (CDNs, widgets, ...)
+    #[arg(long = "third-party-scripts", requires = "fetch_scripts")]
+    third_party_scripts: bool,
+
+    /// Retrieve the source maps of downloaded scripts and unpack the original sources
+    #[arg(long = "source-maps", requires = "fetch_scripts")]
+    source_maps: bool,
+
+    /// Maximum number of scripts downloaded by --fetch-scripts
+    #[arg(long = "max-scripts", default_value_t = 200)]
+    max_scripts: usize,
+
+    /// How robots.txt is used: mine it for paths or respect its rules
+    #[arg(long = "robots", value_enum, default_value_t = robots::RobotsMode::Mine)]
+    robots_mode: robots::RobotsMode,
+
+    /// User-agent token used to select the robots.txt group
+    #[arg(long = "robots-agent", default_value = "*")]
+    robots_agent: String,
+
+    /// Maximum number of sitemap documents fetched (0 disables sitemap discovery)
+    #[arg(long = "max-sitemaps", default_value_t = 50)]
+    max_sitemaps: usize,
+
+    /// Write a structured JSON report of the whole run to this file
+    #[arg(long = "json")]
+    json: Option<PathBuf>,
+
+    /// Write probes.csv and findings.csv into this directory
+    #[arg(long = "csv")]
+    csv: Option<PathBuf>,
+
+    /// Print the initial page to the console without asking
+    #[arg(long = "print-root")]
+    print_root: bool,
+
+    /// Never prompt, use the defaults for every question
+    #[arg(short = 'y', long = "yes", visible_alias = "no-input")]
+    no_input: bool,
+
+    /// Suppress the scanner's debug output
+    #[arg(short = 'q', long = "quiet")]
+    quiet: bool,
+
+    /// Base directory for results, each run goes to <DIR>/<host>/<timestamp>/
+    #[arg(short = 'o', long = "output-dir", default_value = "out")]
+    output_dir: PathBuf,
+
+    /// Handling of responses that match the calibrated "not found" page
+    #[arg(long = "soft404", value_enum, default_value_t = Soft404Mode::Suppress)]
+    soft404_mode: Soft404Mode,
+
+    /// Similarity (0.0-1.0) above which a response counts as a soft-404
+    #[arg(long = "soft404-threshold", default_value_t = 0.95)]
+    soft404_threshold: f64,
+
+    /// Status codes of responses that are kept, e.g. 200,301-399 or all
+    #[arg(long = "match-codes", value_parser = filters::parse_ranges, default_value = filters::DEFAULT_MATCH_CODES)]
+    match_codes: filters::NumberRanges,
+
+    /// Status codes of responses that are dropped, even when matched by --match-codes
+    #[arg(long = "filter-codes", value_parser = filters::parse_ranges)]
+    filter_codes: Option<filters::NumberRanges>,
+
+    /// Body sizes in bytes of responses that are dropped, e.g. 0,1024-2048
+    #[arg(long = "filter-size", value_parser = filters::parse_ranges)]
+    filter_sizes: Option<filters::NumberRanges>,
+
+    /// Word counts of response bodies that are dropped
+    #[arg(long = "filter-words", value_parser = filters::parse_ranges)]
+    filter_words: Option<filters::NumberRanges>,
+
+    /// Drop responses whose body matches this regular expression
+    #[arg(long = "filter-regex")]
+    filter_regex: Option<String>,
+
+    /// Only keep responses whose body matches this regular expression
+    #[arg(long = "match-regex")]
+    match_regex: Option<String>,
+
+    /// Maximum size in bytes of a saved non-HTML resource, larger bodies are truncated
+    #[arg(long = "max-resource-size", default_value_t = 5 * 1024 * 1024)]
+    max_resource_size: usize,
+
+    /// Continue the most recent unfinished run of this target instead of starting a new one
+    #[arg(long = "resume")]
+    resume: bool,
+
+    /// JSON file with additional technology signatures, entries override built-in ones of the same name
+    #[arg(long = "signatures")]
+    signatures: Option<PathBuf>,
+}
 
 fn sanitize_filename(url: &str) -> String {
     url.trim()
@@ -35,42 +349,35 @@ fn sanitize_filename(url: &str) -> String {
         .or_else(|| url.strip_prefix("http://"))
         .unwrap_or(url)
         .trim_end_matches('/')
-        .replace('/', "_")
-        .replace(':', "_")
-        .replace('\\', "_")
-        .replace('*', "_")
-        .replace('?', "_")
-        .replace('"', "_")
-        .replace('|', "_")
-        .replace('<', "_")
-        .replace('>', "_")
-        .replace(' ', "_")
+        .replace(['/', ':', '\\', '*', '?', '"', '|', '<', '>', ' '], "_")
 }
 
-fn create_file_and_scan(
-    client: &Client,
-    base_url: &str,
-    normalized_path: &str,
-    html_content: String,
-) -> Result<(), Box<dyn std::error::Error>> {
+//where the HTML of a page is saved, also used to find it again when resuming
+fn html_file_path(html_dir: &Path, base_url: &str, normalized_path: &str) -> PathBuf {
     // Sanitize path for filename (handle slashes, etc.)
-    let sanitized_path = normalized_path.trim_start_matches('/').replace('/', "_");
-    let filename = if sanitized_path.is_empty() {
-        format!(
-            "{}/{}_root_data.txt",
-            OUTPUT_DIR,
-            sanitize_filename(base_url)
-        )
+    let sanitized_path = normalized_path
+        .trim_start_matches('/')
+        .replace(['/', ':', '\\', '*', '?', '"', '|', '<', '>', ' ', '&'], "_");
+    if sanitized_path.is_empty() {
+        html_dir.join(format!("{}_root_data.txt", sanitize_filename(base_url)))
     } else {
-        format!(
-            "{}/{}_{}_data.txt",
-            OUTPUT_DIR,
+        html_dir.join(format!(
+            "{}_{}_data.txt",
             sanitize_filename(base_url),
             sanitized_path
-        )
-    };
+        ))
+    }
+}
 
-    create_dir_all(OUTPUT_DIR)?;
+fn create_file_and_scan(
+    report: &mut Report,
+    html_dir: &Path,
+    base_url: &str,
+    normalized_path: &str,
+    html_content: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let filename = html_file_path(html_dir, base_url, normalized_path);
+    let filename = filename.to_string_lossy().to_string();
 
     let mut file = File::create(&filename)?;
     file.write_all(html_content.as_bytes())?;
@@ -87,26 +394,122 @@ fn create_file_and_scan(
                 results.scripts.len(),
                 results.comments.len()
             );
+            secrets::print_findings(&results);
+            let page_url = if normalized_path.is_empty() {
+                base_url.to_string()
+            } else {
+                format!("{}{}", base_url.trim_end_matches('/'), normalized_path)
+            };
+            report.record_findings(&page_url, &html_content, results);
         }
         Err(e) => eprintln!("Fehler beim Scannen der Datei {}: {}", filename, e),
     }
     Ok(())
 }
 
-fn download_robots_txt(client: &Client, base_url: &str) -> Result<(), Box<dyn std::error::Error>> {
+//Saves a non-HTML body with an extension matching its content and runs the matching
+//content-aware scanner on it.
+fn save_resource_and_scan(
+    report: &mut Report,
+    resource_dir: &Path,
+    base_url: &str,
+    normalized_path: &str,
+    content_type: &str,
+    body: &[u8],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let sanitized_path = normalized_path
+        .trim_start_matches('/')
+        .replace(['/', ':', '\\', '*', '?', '"', '|', '<', '>', ' ', '&'], "_");
+    let extension = resources::extension_for(content_type, normalized_path);
+    let filename = resource_dir.join(format!(
+        "{}_{}_data.{}",
+        sanitize_filename(base_url),
+        sanitized_path,
+        extension
+    ));
+
+    let mut file = File::create(&filename)?;
+    file.write_all(body)?;
+    println!("Resource saved in {}", filename.display());
+
+    let kind = resources::ResourceKind::from_extension(&extension);
+    let content = String::from_utf8_lossy(body);
+    match resources::scan_resource(&content, kind, &filename.to_string_lossy()) {
+        Ok(results) => {
+            println!(
+                "[+] Scan results for {}: Emails: {}, Links: {}, APIs: {}, Secrets: {}",
+                normalized_path,
+                results.emails.len(),
+                results.links.len(),
+                results.api_endpoints.len(),
+                results.secrets.len()
+            );
+            secrets::print_findings(&results);
+            let resource_url = format!("{}{}", base_url.trim_end_matches('/'), normalized_path);
+            report.record_findings(&resource_url, &content, results);
+        }
+        Err(e) => eprintln!("Error scanning {}: {}", filename.display(), e),
+    }
+    Ok(())
+}
+
+//scripts and source maps are saved under their own origin, like crawled pages
+fn save_script_and_scan(
+    report: &mut Report,
+    resource_dir: &Path,
+    url: &Url,
+    content_type: &str,
+    body: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let base = format!("{}/", url.origin().ascii_serialization());
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    save_resource_and_scan(report, resource_dir, &base, &path, content_type, body.as_bytes())
+}
+
+//writes the original sources of a source map to found_resources/sourcemaps/<map>/ and scans them
+fn unpack_source_map_and_scan(
+    report: &mut Report,
+    resource_dir: &Path,
+    map_url: &Url,
+    map: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = resource_dir.join("sourcemaps").join(sanitize_filename(map_url.as_str()));
+    let sources = javascript::unpack_source_map(map, &dir)?;
+    println!("Unpacked {} original sources from {} into {}", sources.len(), map_url, dir.display());
 
-    create_dir_all(OUTPUT_DIR)?;
+    for (source, path) in sources {
+        let kind = resources::ResourceKind::from_extension(
+            &path.extension().map(|ext| ext.to_string_lossy()).unwrap_or_default(),
+        );
+        let content = std::fs::read_to_string(&path)?;
+        let results = resources::scan_resource(&content, kind, &path.to_string_lossy())?;
+        secrets::print_findings(&results);
+        report.record_findings(&source, &content, results);
+    }
+    Ok(())
+}
 
+async fn download_robots_txt(
+    client: &HttpClient,
+    base_url: &str,
+    html_dir: &Path,
+    report: &mut Report,
+) -> Result<(), Box<dyn std::error::Error>> {
     let robots_url = format!("{}robots.txt", base_url);
-    match client.get(&robots_url).timeout(Duration::from_secs(10)).send() {
+    let started = Instant::now();
+    match client.get(&robots_url).await {
         Ok(resp) => {
+            report.record_probe(ProbeRecord::from_response(&robots_url, &resp, started));
             if resp.status().is_success() {
-                match resp.text() {
+                match resp.text().await {
                     Ok(content) => {
-                        let robots_file_path = format!("{}/robots.txt", OUTPUT_DIR);
+                        let robots_file_path = html_dir.join("robots.txt");
                         let mut file = File::create(&robots_file_path)?;
                         file.write_all(content.as_bytes())?;
-                        println!("robots.txt saved in {}", robots_file_path);
+                        println!("robots.txt saved in {}", robots_file_path.display());
                     }
                     Err(e) => eprintln!("Failed to get text from {}: {}", robots_url, e),
                 }
@@ -118,91 +521,186 @@ fn download_robots_txt(client: &Client, base_url: &str) -> Result<(), Box<dyn st
             }
         }
         Err(e) => {
-            println!("Request failed for {}: {}", robots_url, e);
+            report.record_probe(ProbeRecord::from_error(&robots_url, &e, started));
+            println!("Request failed for {} ({}): {}", robots_url, ErrorClass::classify(&e), e);
         }
     }
     Ok(())
 }
 
-fn parse_robots_txt(robots_txt_path: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let mut paths = Vec::new();
-
-    if !Path::new(robots_txt_path).exists() {
-        return Ok(paths);
+//a failed write only costs the ability to skip this path on resume, the scan goes on
+fn record_progress(progress_log: &mut ProgressLog, pb: &ProgressBar, progress: &PathProgress) {
+    if let Err(e) = progress_log.record(progress) {
+        print_above(pb, format!("Could not save progress for {}: {}", progress.path, e));
     }
+}
 
-    let file = File::open(robots_txt_path)?;
-    let reader = BufReader::new(file);
+//pb.println is swallowed when the bar is hidden, e.g. when stderr is not a terminal
+fn print_above(pb: &ProgressBar, message: String) {
+    if pb.is_hidden() {
+        println!("{}", message);
+    } else {
+        pb.println(message);
+    }
+}
 
-    for line in reader.lines() {
-        let line = line?;
-        let trimmed_line = line.trim_start();
+//how the phase after the scan loop ended, a Ctrl-C stops it early
+fn phase_outcome() -> &'static str {
+    if state::interrupted() { "interrupted" } else { "completed" }
+}
 
-        if trimmed_line.starts_with("Disallow:") || trimmed_line.starts_with("Allow:") {
-            let parts: Vec<&str> = trimmed_line.splitn(2, ':').collect();
-            if parts.len() == 2 {
-                let path = parts[1].trim();
-                if !path.is_empty() && path != "*" {
-                    //make sure the path starts with a slash "/"
-                    let normalized_path = if path.starts_with('/') {
-                        path.to_string()
-                    } else {
-                        format!("/{}", path)
-                    };
-                    paths.push(normalized_path);
-                }
-            }
+//the generated wordlist paths, a read error ends them with a warning instead of silently
+fn wordlist_paths(
+    paths: Box<dyn Iterator<Item = io::Result<String>>>,
+    pb: &ProgressBar,
+    wordlist_names: &str,
+) -> Box<dyn Iterator<Item = String>> {
+    let (pb, wordlist_names) = (pb.clone(), wordlist_names.to_string());
+    Box::new(paths.map_while(move |path| match path {
+        Ok(path) => Some(path),
+        Err(e) => {
+            print_above(&pb, format!("Warning: reading the wordlist {} stopped early: {}", wordlist_names, e));
+            None
         }
-        //User agent comments are ignored
-    }
-    Ok(paths)
+    }))
 }
 
-fn download_and_save(args: Args) -> Result<(), Box<dyn std::error::Error>> {
-    let selected_url = &args.url;
-    let base_url = format!("{}/", selected_url.trim_end_matches('/'));
+//probes a single path, used by the worker pool in download_and_save
+async fn probe_path(
+    client: &HttpClient,
+    base_url: &str,
+    path: String,
+    max_resource_size: usize,
+    filter: &ResponseFilter,
+) -> ProbeResult {
+    let url = format!("{}{}", base_url.trim_end_matches('/'), path);
 
-    create_dir_all(OUTPUT_DIR)?;
+    let mut record;
+    let started = Instant::now();
+    let outcome = match client.get(&url).await {
+        Ok(resp) => {
+            record = ProbeRecord::from_response(&url, &resp, started);
+            let status = resp.status();
+            if !filter.matches_status(status.as_u16()) {
+                if filter.filter_codes.as_ref().is_some_and(|codes| codes.contains(status.as_u16() as u64)) {
+                    ProbeOutcome::Filtered
+                } else {
+                    ProbeOutcome::Status(status)
+                }
+            } else if status.is_redirection() && record.redirect_target.is_some() {
+                ProbeOutcome::Redirect(resp.text().await.unwrap_or_default())
+            } else {
+                let content_type = resp
+                    .headers()
+                    .get("content-type")
+                    .and_then(|ct| ct.to_str().ok())
+                    .unwrap_or("")
+                    .to_string();
 
-    let client = Client::new();
-    let response = client.get(&base_url).send()?.error_for_status()?;
-    let html_content = response.text()?;
+                if content_type.contains("text/html") {
+                    match resp.text().await {
+                        Ok(html) => {
+                            record.size = Some(html.len() as u64);
+                            ProbeOutcome::Html(html)
+                        }
+                        Err(e) => {
+                            record.set_error(&e);
+                            ProbeOutcome::TextError(e)
+                        }
+                    }
+                } else if resp.content_length().is_some_and(|size| size > max_resource_size as u64) {
+                    let size = resp.content_length().unwrap_or_default();
+                    ProbeOutcome::Oversized { content_type, size }
+                } else {
+                    match http::read_body_limited(resp, max_resource_size).await {
+                        Ok((body, truncated)) => {
+                            record.size = Some(body.len() as u64);
+                            ProbeOutcome::Resource {
+                                content_type,
+                                body,
+                                truncated,
+                            }
+                        }
+                        Err(e) => {
+                            record.set_error(&e);
+                            ProbeOutcome::TextError(e)
+                        }
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            record = ProbeRecord::from_error(&url, &e, started);
+            ProbeOutcome::RequestError(e)
+        }
+    };
 
-    let data = Data {
-        data: vec![],
-        html_content: html_content.clone(),
+    //size, word and regex filters look at the body that was read
+    let outcome = match outcome {
+        ProbeOutcome::Html(body) | ProbeOutcome::Redirect(body) if !filter.matches_body(body.len(), Some(&body)) => {
+            ProbeOutcome::Filtered
+        }
+        ProbeOutcome::Resource { body, .. }
+            if !filter.matches_body(body.len(), Some(&String::from_utf8_lossy(&body))) =>
+        {
+            ProbeOutcome::Filtered
+        }
+        ProbeOutcome::Oversized { size, .. } if !filter.matches_body(size as usize, None) => ProbeOutcome::Filtered,
+        outcome => outcome,
     };
+    record.filtered = matches!(outcome, ProbeOutcome::Filtered | ProbeOutcome::Status(_));
 
-    println!("Should the initial page data be printed to the console? (y/n): ");
-    let input = get_user_input();
-    if input.eq_ignore_ascii_case("y") {
-        println!("{}", data.html_content);
-    }
+    ProbeResult { path, url, record, outcome }
+}
 
-    create_file_and_scan(&client, &base_url, "", data.html_content)?;
+//The path a probe landed on after following redirects, relative to the target;
+//None when the redirects led to another host.
+fn landing_path(record: &ProbeRecord) -> Option<String> {
+    let target = record.redirect_target.as_deref()?;
+    if record.off_site_redirect {
+        return None;
+    }
+    let target = Url::parse(target).ok()?;
+    Some(match target.query() {
+        Some(query) => format!("{}?{}", target.path(), query),
+        None => target.path().to_string(),
+    })
+}
 
-    download_robots_txt(&client, &base_url)?;
-    let robots_txt_path = format!("{}/robots.txt", OUTPUT_DIR);
-    let robots_paths = parse_robots_txt(&robots_txt_path)?;
+//The fixed paths of a new scan: predefined, robots.txt and sitemaps, without duplicates.
+//The wordlist is not part of them, it is streamed into the scan behind them.
+//`robots_group` is only given when robots.txt is respected.
+async fn collect_paths(
+    args: &Args,
+    client: &HttpClient,
+    base_url: &str,
+    paths: &OutputPaths,
+    robots_info: &robots::RobotsInfo,
+    robots_group: Option<&robots::RobotsGroup>,
+    report: &mut Report,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut robots_paths = robots_info.candidate_paths();
+    if let Some(group) = robots_group {
+        robots_paths.retain(|path| group.is_allowed(path));
+    }
     println!("Loaded {} paths from robots.txt.", robots_paths.len());
+    if let Some(host) = &robots_info.host {
+        println!("robots.txt declares preferred host: {}", host);
+    }
+    for sitemap in &robots_info.sitemaps {
+        println!("robots.txt lists sitemap: {}", sitemap);
+    }
 
-    let wordlist_path = &args.wordlist;
-    let wordlist_paths = if Path::new(wordlist_path).exists() {
-        let file = File::open(wordlist_path)?;
-        let reader = BufReader::new(file);
-
-        let lines: Result<Vec<String>, _> = reader.lines().collect();
-        let paths: Vec<String> = lines?
-            .into_iter()
-            .map(|line| line.trim().to_string())
-            .filter(|line| !line.is_empty() && !line.starts_with('#'))
-            .collect();
-        println!("Loaded {} paths from {}.", paths.len(), wordlist_path);
-        paths
+    let sitemap_entries = if args.max_sitemaps > 0 {
+        sitemap::discover_sitemaps(client, base_url, &robots_info.sitemaps, args.max_sitemaps, |record| report.record_probe(record)).await
     } else {
-        eprintln!("Warning: Wordlist file '{}' not found. Continuing with predefined and robots.txt paths only.", wordlist_path);
         vec![]
     };
+    let sitemap_paths = sitemap::same_origin_paths(&sitemap_entries, base_url);
+    println!("Loaded {} paths from sitemaps.", sitemap_paths.len());
+    if !sitemap_entries.is_empty() {
+        sitemap::write_sitemap_intel(&sitemap_entries, &paths.intel_dir)?;
+    }
 
     //Predefined Paths
     let predefined_test_paths = vec![
@@ -224,89 +722,781 @@ fn download_and_save(args: Args) -> Result<(), Box<dyn std::error::Error>> {
         "/web.config",
     ];
 
-    let all_paths_to_test: Vec<String> = predefined_test_paths
+    let mut all_paths_to_test: Vec<String> = predefined_test_paths
         .into_iter()
         .map(String::from)
-        .chain(robots_paths.into_iter())
-        .chain(wordlist_paths.into_iter())
+        .chain(robots_paths)
+        .chain(sitemap_paths)
         .collect();
 
-    let total_count = all_paths_to_test.len();
-    let pb = ProgressBar::new(total_count as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}")?
-            .progress_chars("#>-"),
-    );
+    //the same path can come from several sources, it is only probed once
+    let mut seen_paths = HashSet::new();
+    all_paths_to_test.retain(|path| seen_paths.insert(path.clone()));
+
+    if let Some(group) = robots_group {
+        let before = all_paths_to_test.len();
+        all_paths_to_test.retain(|path| group.is_allowed(path));
+        println!(
+            "Skipping {} paths disallowed by robots.txt.",
+            before - all_paths_to_test.len()
+        );
+    }
+
+    Ok(all_paths_to_test)
+}
+
+//`resume` continues the run in `paths`, its state file supplies the path list
+async fn download_and_save(args: Args, paths: &OutputPaths, resume: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let selected_url = &args.url;
+    let base_url = format!("{}/", selected_url.trim_end_matches('/'));
+
+    paths.create()?;
+    if resume {
+        println!("Resuming the run in {}", paths.run_dir.display());
+    } else {
+        println!("Saving results of this run in {}", paths.run_dir.display());
+    }
+    let mut report = Report::open(&base_url, &paths.run_dir)?;
+
+    let cookies = Arc::new(Jar::default());
+    if let Some(cookie_file) = &args.cookies {
+        let loaded = session::load_cookie_file(cookie_file, &cookies)?;
+        println!("Loaded {} cookies from {}.", loaded, cookie_file.display());
+    }
+    let mut proxies = args.proxies.clone();
+    if let Some(proxy_list) = &args.proxy_list {
+        let loaded = http::load_proxy_list(proxy_list)?;
+        println!("Loaded {} proxies from {}.", loaded.len(), proxy_list.display());
+        proxies.extend(loaded);
+    }
+    let check_proxies = !proxies.is_empty();
+    let client = HttpClient::new(
+        ClientConfig {
+            timeout: Duration::from_secs(args.timeout),
+            headers: args.headers.clone(),
+            user_agent: args.user_agent.clone(),
+            authorization: args.auth_basic.clone().or_else(|| args.auth_bearer.clone()),
+            auth_url: base_url.clone(),
+            cookies,
+            proxies,
+            ca_certificate: args.proxy_ca.clone(),
+            max_redirects: if args.no_follow_redirects { 0 } else { args.max_redirects },
+        },
+        ThrottleConfig {
+            rate: args.rate,
+            delay: Duration::from_millis(args.delay),
+            jitter: Duration::from_millis(args.jitter),
+        },
+        RetryPolicy {
+            retries: args.retries,
+            backoff: Duration::from_millis(args.retry_backoff),
+            errors: args.retry_on.clone(),
+            statuses: args.retry_statuses.clone(),
+        },
+    )?;
+
+    if check_proxies {
+        println!("Checking proxies...");
+        if client.check_proxies(&base_url).await == 0 {
+            return Err("None of the proxies can reach the target.".into());
+        }
+    }
+
+    //a session from a cookie file or a login must survive the scan, so logout links are avoided
+    let keep_session = args.cookies.is_some() || args.login_url.is_some();
+    if let (Some(login_url), Some(login_data)) = (&args.login_url, &args.login_data) {
+        let login_url = Url::parse(&base_url)?.join(login_url)?;
+        session::form_login(&client, &login_url, &session::parse_form_data(login_data), |record| {
+            report.record_probe(record)
+        })
+        .await?;
+    }
+
+    let started = Instant::now();
+    let response = client.get(&base_url).await?.error_for_status()?;
+    let mut root_record = ProbeRecord::from_response(&base_url, &response, started);
+    if let Some(target) = &root_record.redirect_target {
+        println!("Note: {} redirects to {}, consider scanning that URL instead.", base_url, target);
+    }
+    let html_content = response.text().await?;
+    root_record.size = Some(html_content.len() as u64);
+    //a resumed run fetches the root page again for its links, but it was recorded and scanned before
+    let root_recorded = resume && report.probes.iter().any(|probe| probe.url == base_url);
+    if !root_recorded {
+        report.record_probe(root_record);
+    }
+
+    //only ask when a person can answer, cron jobs and pipelines get the default
+    let print_root = if args.print_root {
+        true
+    } else if args.no_input || !io::stdin().is_terminal() {
+        false
+    } else {
+        println!("Should the initial page data be printed to the console? (y/n): ");
+        get_user_input().is_some_and(|input| input.eq_ignore_ascii_case("y"))
+    };
+    if print_root {
+        println!("{}", html_content);
+    }
+
+    //pages already fetched by the scan, the crawler starts from their links
+    let mut crawl_seeds = Vec::new();
+    if args.crawl {
+        crawl_seeds.push((Url::parse(&base_url)?, html_content.clone()));
+    }
+    //scripts linked from every saved page, downloaded after the scan with --fetch-scripts
+    let mut linked_scripts = Vec::new();
+    if args.fetch_scripts {
+        linked_scripts.extend(javascript::script_urls(&Url::parse(&base_url)?, &html_content));
+    }
+    //directory listings found by the scan, their files are downloaded afterwards with --harvest-listings
+    let mut listing_pages = Vec::new();
+    if listing::is_directory_listing(&html_content) {
+        println!("Directory listing: {}", base_url);
+        if args.harvest_listings {
+            listing_pages.push((Url::parse(&base_url)?, html_content.clone()));
+        }
+    }
+
+    if !root_recorded {
+        create_file_and_scan(&mut report, &paths.html_dir, &base_url, "", html_content)?;
+    }
 
+    //a resumed run keeps the robots.txt it saved, its rules still apply in respect mode
+    if !resume {
+        download_robots_txt(&client, &base_url, &paths.html_dir, &mut report).await?;
+    }
+    let robots_txt_path = paths.html_dir.join("robots.txt");
+    let robots_info = robots::load_robots_txt(&robots_txt_path)?;
+    let robots_group = robots_info.group_for(&args.robots_agent).cloned();
+    let respect_robots = args.robots_mode == robots::RobotsMode::Respect;
+
+    //Crawl-delay is only honoured when respecting robots.txt, it forces a single worker
+    let crawl_delay = if respect_robots {
+        robots_group.as_ref().and_then(|group| group.crawl_delay())
+    } else {
+        None
+    };
+    if let Some(delay) = crawl_delay {
+        println!("Honouring robots.txt Crawl-delay of {:.1}s.", delay.as_secs_f64());
+        client.raise_delay(delay);
+    }
+
+    let mut scan_state = if resume {
+        ScanState::load(&paths.run_dir)?
+    } else {
+        let scan_state = ScanState {
+            target: base_url.clone(),
+            started_at: report.started_at,
+            paths: collect_paths(
+                &args,
+                &client,
+                &base_url,
+                paths,
+                &robots_info,
+                if respect_robots { robots_group.as_ref() } else { None },
+                &mut report,
+            )
+            .await?,
+            finished: false,
+        };
+        scan_state.save(&paths.run_dir)?;
+        scan_state
+    };
+    //the reports of a resumed run cover it from its original start
+    report.started_at = scan_state.started_at;
+
+    let specs = wordlist::parse_wordlist_specs(&args.wordlists);
+    let mutations = wordlist::Mutations {
+        extensions: args.extensions.clone(),
+        backups: args.backups,
+        case_variants: args.case_variants,
+    };
+    let wordlist_names = specs.iter().map(|spec| spec.path.as_str()).collect::<Vec<_>>().join(", ");
+
+    //paths that failed without a response get another chance on resume
+    let progress = state::load_progress(&paths.run_dir)?;
+    if resume {
+        let probed = progress.values().filter(|entry| entry.outcome != "error").count();
+        println!("{} paths were already probed and are skipped.", probed);
+        if specs.iter().any(wordlist::WordlistSpec::is_stdin) {
+            println!("Note: the wordlist is read from stdin again, pipe in the same list to continue where the run stopped.");
+        }
+        //pages saved before the interruption still feed the crawler and the script download
+        for entry in progress.values().filter(|entry| entry.outcome == "html") {
+            if !args.crawl && !args.fetch_scripts && !args.harvest_listings {
+                break;
+            }
+            let saved_as = entry.saved_as.as_deref().unwrap_or(&entry.path);
+            let saved = html_file_path(&paths.html_dir, &base_url, saved_as);
+            if let Ok(html) = std::fs::read_to_string(saved)
+                && let Ok(page_url) = Url::parse(&format!("{}{}", base_url.trim_end_matches('/'), saved_as))
+            {
+                if args.fetch_scripts {
+                    linked_scripts.extend(javascript::script_urls(&page_url, &html));
+                }
+                if args.harvest_listings && listing::is_directory_listing(&html) {
+                    listing_pages.push((page_url.clone(), html.clone()));
+                }
+                if args.crawl {
+                    crawl_seeds.push((page_url, html));
+                }
+            }
+        }
+    }
+
+    let soft404_detector = if args.soft404_mode == Soft404Mode::Off {
+        None
+    } else {
+        println!("Calibrating soft-404 detection...");
+        Some(Soft404Detector::calibrate(&client, &base_url, args.soft404_threshold, |record| report.record_probe(record)).await)
+    };
+    let mut soft_404_count = 0;
+
+    //the total is unknown until the wordlist has been counted in the background, until then
+    //the bar only shows how many paths were probed
+    let pb = ProgressBar::no_length();
+    pb.set_style(ProgressStyle::default_spinner().template("{spinner:.green} [{elapsed_precise}] {pos} paths probed ({per_sec}) {msg}")?);
+    let bar_style = ProgressStyle::default_bar()
+        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}")?
+        .progress_chars("#>-");
+    http::set_progress_bar(Some(pb.clone()));
+    let fixed_count = scan_state.paths.len() as u64;
+    let mut count_task = {
+        let (specs, mutations, template) = (specs.clone(), mutations.clone(), args.path_template.clone());
+        tokio::task::spawn_blocking(move || wordlist::count_paths(&specs, template.as_deref(), &mutations))
+    };
+    let mut counted = false;
+
+    let concurrency = if crawl_delay.is_some() {
+        1
+    } else {
+        args.concurrency.max(1)
+    };
     println!(
-        "Starting scan with {} total paths (predefined + robots.txt + wordlist)...",
-        total_count
+        "Starting scan with {} predefined, robots.txt and sitemap paths, followed by the wordlist {}, using {} workers...",
+        fixed_count, wordlist_names, concurrency
     );
 
+    //the wordlist is read and expanded as the scan goes, so the first request goes out at once
+    let generated_paths = match wordlist::generate_paths(&specs, args.path_template.as_deref(), &mutations) {
+        Ok(generated) => wordlist_paths(generated, &pb, &wordlist_names),
+        Err(e) => {
+            eprintln!(
+                "Warning: Wordlist '{}' could not be read ({}). Continuing with predefined and robots.txt paths only.",
+                wordlist_names, e
+            );
+            Box::new(std::iter::empty())
+        }
+    };
+    let respected_group = if respect_robots { robots_group.as_ref() } else { None };
+    let mut seen_paths = wordlist::SeenPaths::default();
+    let skipped = Cell::new(0u64);
+    let skipped_disallowed = Cell::new(0usize);
+    let skipped_logout = Cell::new(0usize);
+    let mut source: Box<dyn Iterator<Item = String>> = Box::new(scan_state.paths.clone().into_iter().chain(generated_paths));
+    let mut keep_path = |path: &String| {
+        //the same path can come from several sources, it is only probed once
+        let keep = if !seen_paths.insert(path) || progress.get(path).is_some_and(|entry| entry.outcome != "error") {
+            false
+        } else if respected_group.is_some_and(|group| !group.is_allowed(path)) {
+            skipped_disallowed.set(skipped_disallowed.get() + 1);
+            false
+        } else if keep_session && session::is_logout_path(path) {
+            skipped_logout.set(skipped_logout.get() + 1);
+            false
+        } else {
+            true
+        };
+        if !keep {
+            skipped.set(skipped.get() + 1);
+            if pb.length().is_some() {
+                pb.dec_length(1);
+            }
+        }
+        keep
+    };
+
+    let mut recursion = args.recursive.then(|| {
+        recursion::Recursion::new(recursion::RecursionConfig {
+            max_depth: args.recursion_depth,
+            max_dirs: args.max_branch_dirs,
+            max_hits: args.max_branch_hits,
+            exclude: args.recursion_exclude.clone(),
+        })
+    });
+    if let Some(recursion) = recursion.as_mut() {
+        //directories queued before an interruption are brute-forced again, paths probed below them are skipped
+        let mut directories: Vec<(usize, &str)> = progress
+            .values()
+            .filter_map(|entry| entry.directory_depth.map(|depth| (depth, entry.path.as_str())))
+            .collect();
+        directories.sort();
+        for (depth, path) in directories {
+            let directory = recursion::directory_of(path, None, None).unwrap_or_else(|| format!("{}/", path));
+            recursion.requeue(&directory, depth);
+        }
+    }
+    //each branch adds the wordlist once more to the total
+    let mut wordlist_count: Option<u64> = None;
+    let mut branches_started = 1;
+
+    let max_resource_size = args.max_resource_size;
+    let compile = |pattern: &Option<String>, flag: &str| -> Result<Option<Regex>, Box<dyn std::error::Error>> {
+        pattern
+            .as_deref()
+            .map(|pattern| Regex::new(pattern).map_err(|e| format!("Invalid {} pattern: {}", flag, e).into()))
+            .transpose()
+    };
+    let filter = ResponseFilter {
+        match_codes: args.match_codes.clone(),
+        filter_codes: args.filter_codes.clone(),
+        filter_sizes: args.filter_sizes.clone(),
+        filter_words: args.filter_words.clone(),
+        filter_regex: compile(&args.filter_regex, "--filter-regex")?,
+        match_regex: compile(&args.match_regex, "--match-regex")?,
+    };
+    let mut filtered_count = 0;
+
+    //paths saved so far, several probes can be redirected to the same page
+    let mut saved_paths: HashSet<String> = HashSet::new();
+    let mut progress_log = ProgressLog::open(&paths.run_dir)?;
+
     //Scan Loop
-    for path in all_paths_to_test {
-        pb.inc(1);
-
-        let normalized_path = path;
-
-        let url = format!("{}{}", base_url.trim_end_matches('/'), normalized_path);
-
-        match client.get(&url).timeout(Duration::from_secs(10)).send() {
-            Ok(resp) => {
-                if resp.status().is_success() {
-                    let content_type = resp
-                        .headers()
-                        .get("content-type")
-                        .and_then(|ct| ct.to_str().ok())
-                        .unwrap_or("");
-
-                    if content_type.contains("text/html") {
-                        match resp.text() {
-                            Ok(html) => {
-                                //Saves a found HTML file and scans it.
-                                if let Err(e) = create_file_and_scan(
-                                    &client,
-                                    &base_url,
-                                    &normalized_path,
-                                    html,
-                                ) {
-                                    eprintln!("Error processing {}: {}", url, e);
-                                }
-                            }
-                            Err(e) => eprintln!("Failed to get text from {}: {}", url, e),
+    //Probes run concurrently, but `buffered` hands the results back in path order,
+    //so saving and scanning happens exactly as in a sequential run.
+    //With --recursive, the scan runs once more for every directory queued, breadth first.
+    loop {
+        let mut results = stream::iter(source.by_ref().filter(&mut keep_path))
+            .map(|path| {
+                let client = &client;
+                let base_url = &base_url;
+                let pb = &pb;
+                let filter = &filter;
+                async move {
+                    let result = probe_path(client, base_url, path, max_resource_size, filter).await;
+                    pb.inc(1);
+                    result
+                }
+            })
+            .buffered(concurrency);
+
+        loop {
+            //on Ctrl-C the probes in flight are dropped, they are not in the progress log and run again on resume
+            let result = tokio::select! {
+                result = results.next() => result,
+                count = &mut count_task, if !counted => {
+                    counted = true;
+                    if let Ok(Ok(Some(count))) = count {
+                        wordlist_count = Some(count);
+                        pb.set_length((fixed_count + count * branches_started).saturating_sub(skipped.get()));
+                        pb.set_style(bar_style.clone());
+                        print_above(&pb, format!("The wordlist {} yields {} paths.", wordlist_names, count));
+                    }
+                    continue;
+                }
+                _ = state::wait_for_interrupt() => None,
+            };
+            let Some(result) = result else {
+                break;
+            };
+            let ProbeResult { path, url, mut record, outcome } = result;
+            //a catch-all can answer with JSON or plain text as well, resources are compared like pages
+            let body = match &outcome {
+                ProbeOutcome::Html(body) | ProbeOutcome::Redirect(body) => Some(Cow::Borrowed(body.as_str())),
+                ProbeOutcome::Resource { body, .. } => Some(String::from_utf8_lossy(body)),
+                _ => None,
+            };
+            if let Some(body) = body
+                && let Some(detector) = &soft404_detector
+            {
+                let fingerprint = ResponseFingerprint::new(
+                    record.status.unwrap_or_default(),
+                    &body,
+                    &path,
+                    record.redirect_target.as_deref(),
+                );
+                record.soft_404 = detector.is_soft_404(&fingerprint);
+            }
+            let is_soft_404 = record.soft_404;
+            //error statuses kept through --match-codes are saved like pages, but pointed out
+            let matched_error = record.status.filter(|status| *status >= 400);
+            let mut progress = PathProgress {
+                path: path.clone(),
+                status: record.status,
+                outcome: outcome.name().to_string(),
+                directory_depth: None,
+                saved_as: None,
+            };
+            if !is_soft_404
+                && let Some(summary) = record.redirect_summary()
+            {
+                print_above(&pb, format!("Redirect: {}", summary));
+            }
+
+            //a followed redirect is saved under the path it landed on, and only once
+            let followed_redirect = !record.redirect_chain.is_empty();
+            let save_path = if followed_redirect { landing_path(&record) } else { Some(path.clone()) };
+            let url = match &save_path {
+                Some(save_path) if followed_redirect => format!("{}{}", base_url.trim_end_matches('/'), save_path),
+                _ => url,
+            };
+            //directory-like hits are queued for --recursive
+            if let Some(recursion) = recursion.as_mut()
+                && !is_soft_404
+                && matches!(
+                    outcome,
+                    ProbeOutcome::Html(_) | ProbeOutcome::Resource { .. } | ProbeOutcome::Oversized { .. } | ProbeOutcome::Redirect(_)
+                )
+            {
+                if !recursion.hit() {
+                    //the hit over the limit is still a probe of the run, it is only not followed up
+                    report.record_probe(record);
+                    record_progress(&mut progress_log, &pb, &progress);
+                    print_above(&pb, format!(
+                        "Abandoning {} after {} hits, it seems to answer every path (--max-branch-hits).",
+                        recursion.current().path, args.max_branch_hits
+                    ));
+                    break;
+                }
+                let html = match &outcome {
+                    ProbeOutcome::Html(html) => Some(html.as_str()),
+                    _ => None,
+                };
+                let landing = landing_path(&record);
+                if let Some(directory) = recursion::directory_of(&path, landing.as_deref(), html) {
+                    match recursion.queue(&directory) {
+                        Ok(true) => {
+                            progress.directory_depth = Some(recursion.current().depth + 1);
+                            print_above(&pb, format!("Found directory {}, queued for recursion.", directory));
+                        }
+                        Ok(false) | Err(recursion::Skip::Depth) => {}
+                        Err(recursion::Skip::Excluded) => {
+                            print_above(&pb, format!("Not entering {}: excluded by --recursion-exclude.", directory));
+                        }
+                        Err(recursion::Skip::BranchLimit) => {
+                            print_above(&pb, format!(
+                                "Not entering {}: {} directories are already queued below {} (--max-branch-dirs).",
+                                directory, args.max_branch_dirs, recursion.current().path
+                            ));
                         }
-                    } else {
-                        println!(
-                            "Found non-HTML resource: {} (Content-Type: {})",
-                            url, content_type
-                        );
-                        // Optional: Saving of non HTML resources can be added here.
                     }
-                } else {
-                    println!("Status {}: {}", resp.status(), url);
                 }
             }
+            report.record_probe(record);
+            let already_saved = save_path.as_ref().is_some_and(|save_path| !saved_paths.insert(save_path.clone()));
+            if matches!(outcome, ProbeOutcome::Html(_) | ProbeOutcome::Resource { .. })
+                && !is_soft_404
+                && (save_path.is_none() || already_saved)
+            {
+                progress.outcome = "redirect".to_string();
+                record_progress(&mut progress_log, &pb, &progress);
+                continue;
+            }
+            let path = save_path.unwrap_or(path);
+            if path != progress.path {
+                progress.saved_as = Some(path.clone());
+            }
+
+            match outcome {
+                ProbeOutcome::Html(html) => {
+                    if is_soft_404 {
+                        soft_404_count += 1;
+                        if args.soft404_mode == Soft404Mode::Suppress {
+                            progress.outcome = "soft_404".to_string();
+                            record_progress(&mut progress_log, &pb, &progress);
+                            continue;
+                        }
+                        print_above(&pb, format!("Possible soft-404: {}", url));
+                    }
+                    if let Some(status) = matched_error {
+                        print_above(&pb, format!("Status {} (matched): {}", status, url));
+                    }
+
+                    if listing::is_directory_listing(&html) {
+                        print_above(&pb, format!("Directory listing: {}", url));
+                        if args.harvest_listings
+                            && let Ok(page_url) = Url::parse(&url)
+                        {
+                            listing_pages.push((page_url, html.clone()));
+                        }
+                    }
+                    if args.crawl
+                        && let Ok(page_url) = Url::parse(&url)
+                    {
+                        crawl_seeds.push((page_url, html.clone()));
+                    }
+                    if args.fetch_scripts
+                        && let Ok(page_url) = Url::parse(&url)
+                    {
+                        linked_scripts.extend(javascript::script_urls(&page_url, &html));
+                    }
+
+                    //Saves a found HTML file and scans it.
+                    if let Err(e) = pb.suspend(|| create_file_and_scan(&mut report, &paths.html_dir, &base_url, &path, html)) {
+                        print_above(&pb, format!("Error processing {}: {}", url, e));
+                    }
+                }
+                ProbeOutcome::Resource {
+                    content_type,
+                    body,
+                    truncated,
+                } => {
+                    if is_soft_404 {
+                        soft_404_count += 1;
+                        if args.soft404_mode == Soft404Mode::Suppress {
+                            progress.outcome = "soft_404".to_string();
+                            record_progress(&mut progress_log, &pb, &progress);
+                            continue;
+                        }
+                        print_above(&pb, format!("Possible soft-404: {}", url));
+                    }
+                    if let Some(status) = matched_error {
+                        print_above(&pb, format!("Status {} (matched): {}", status, url));
+                    }
+                    print_above(&pb, format!(
+                        "Found non-HTML resource: {} (Content-Type: {}, {} bytes{})",
+                        url,
+                        content_type,
+                        body.len(),
+                        if truncated { ", truncated" } else { "" }
+                    ));
+                    let saved = pb.suspend(|| {
+                        save_resource_and_scan(
+                            &mut report,
+                            &paths.resource_dir,
+                            &base_url,
+                            &path,
+                            &content_type,
+                            &body,
+                        )
+                    });
+                    if let Err(e) = saved {
+                        print_above(&pb, format!("Error processing {}: {}", url, e));
+                    }
+                }
+                ProbeOutcome::Oversized { content_type, size } => {
+                    print_above(&pb, format!(
+                        "Found non-HTML resource: {} (Content-Type: {}, {} bytes, not saved: larger than --max-resource-size)",
+                        url, content_type, size
+                    ));
+                }
+                ProbeOutcome::Redirect(_) => {
+                    if is_soft_404 {
+                        soft_404_count += 1;
+                        if args.soft404_mode == Soft404Mode::Suppress {
+                            progress.outcome = "soft_404".to_string();
+                        } else {
+                            print_above(&pb, format!("Possible soft-404: {}", url));
+                        }
+                    }
+                }
+                ProbeOutcome::Status(status) => {
+                    print_above(&pb, format!("Status {}: {}", status, url));
+                }
+                ProbeOutcome::Filtered => {
+                    filtered_count += 1;
+                }
+                ProbeOutcome::TextError(e) => {
+                    print_above(&pb, format!("Failed to get text from {}: {}", url, e));
+                }
+                ProbeOutcome::RequestError(e) => {
+                    print_above(&pb, format!("Request failed for {} ({}): {}", url, ErrorClass::classify(&e), e));
+                }
+            }
+            record_progress(&mut progress_log, &pb, &progress);
+        }
+        drop(results);
+
+        if state::interrupted() {
+            break;
+        }
+        let Some(branch) = recursion.as_mut().and_then(recursion::Recursion::next_branch) else {
+            break;
+        };
+        print_above(&pb, format!("Recursing into {} (depth {})", branch.path, branch.depth));
+        let prefix = branch.path.trim_end_matches('/').to_string();
+        source = match wordlist::generate_paths(&specs, args.path_template.as_deref(), &mutations) {
+            Ok(generated) => Box::new(wordlist_paths(generated, &pb, &wordlist_names).map(move |path| format!("{}{}", prefix, path))),
             Err(e) => {
-                println!("Request failed for {}: {}", url, e);
+                print_above(&pb, format!("Wordlist '{}' could not be read again ({}), skipping {}", wordlist_names, e, branch.path));
+                continue;
             }
+        };
+        branches_started += 1;
+        if let Some(count) = wordlist_count {
+            pb.inc_length(count);
         }
     }
-    pb.finish_with_message("Scan completed.");
+    if state::interrupted() {
+        pb.abandon_with_message("Scan interrupted.");
+    } else {
+        pb.finish_with_message("Scan completed.");
+    }
+    http::set_progress_bar(None);
+    if skipped_disallowed.get() > 0 {
+        println!("Skipped {} wordlist paths disallowed by robots.txt.", skipped_disallowed.get());
+    }
+    if skipped_logout.get() > 0 {
+        println!("Skipped {} paths that look like a logout, to keep the session.", skipped_logout.get());
+    }
+    if soft_404_count > 0 {
+        let action = if args.soft404_mode == Soft404Mode::Suppress { "Suppressed" } else { "Flagged" };
+        println!("{} {} responses matching the soft-404 baseline.", action, soft_404_count);
+    }
+    if filtered_count > 0 {
+        println!("Filtered out {} responses with --filter-*/--match-regex.", filtered_count);
+    }
+
+    //files requested by the harvest, the crawler does not request them again
+    let mut harvested_urls: HashSet<Url> = HashSet::new();
+    if args.harvest_listings && !listing_pages.is_empty() && !state::interrupted() {
+        let config = listing::HarvestConfig {
+            max_files: args.harvest_max_files,
+            max_size: args.harvest_max_size,
+            concurrency,
+            robots: if respect_robots { robots_group.clone() } else { None },
+        };
+        println!("Harvesting {} directory listings (at most {} downloads)...", listing_pages.len(), config.max_files);
+        let files_saved = listing::harvest(&client, listing_pages, &config, |file| {
+            report.record_probe(file.record);
+            harvested_urls.insert(file.url.clone());
+            let Some(body) = file.body else {
+                return Ok(());
+            };
+            let base = format!("{}/", file.url.origin().ascii_serialization());
+            let path = file.url.path();
+            if file.content_type.contains("text/html") {
+                let html = String::from_utf8_lossy(&body).into_owned();
+                //harvested pages are crawled from, not fetched by the crawler a second time
+                if args.crawl {
+                    crawl_seeds.push((file.url.clone(), html.clone()));
+                }
+                create_file_and_scan(&mut report, &paths.html_dir, &base, path, html)
+            } else {
+                save_resource_and_scan(&mut report, &paths.resource_dir, &base, path, &file.content_type, &body)
+            }
+        })
+        .await;
+        println!("Harvest {}, {} files downloaded.", phase_outcome(), files_saved);
+    }
+
+    if args.crawl && !state::interrupted() {
+        let config = crawler::CrawlConfig {
+            max_depth: args.max_depth,
+            max_pages: args.max_pages,
+            allowed_hosts: args
+                .allow_hosts
+                .iter()
+                .map(|host| host.to_ascii_lowercase())
+                .collect::<HashSet<_>>(),
+            concurrency,
+            robots: if respect_robots { robots_group.clone() } else { None },
+            keep_session,
+        };
+
+        println!(
+            "Starting crawl from {} pages (max depth {}, max pages {})...",
+            crawl_seeds.len(),
+            config.max_depth,
+            config.max_pages
+        );
+        let origin = Url::parse(&base_url)?;
+        let pages_crawled = crawler::crawl(&client, &origin, crawl_seeds, harvested_urls, &config, |page_url, record, html| {
+            report.record_probe(record);
+            let Some(html) = html else {
+                return Ok(());
+            };
+            if args.fetch_scripts {
+                linked_scripts.extend(javascript::script_urls(page_url, &html));
+            }
+
+            //pages on allow-listed hosts are saved under their own origin
+            let page_base = format!("{}/", page_url.origin().ascii_serialization());
+            let page_path = match page_url.query() {
+                Some(query) => format!("{}?{}", page_url.path(), query),
+                None => page_url.path().to_string(),
+            };
+            create_file_and_scan(&mut report, &paths.html_dir, &page_base, &page_path, html)
+        })
+        .await;
+        println!("Crawl {}, {} pages fetched.", phase_outcome(), pages_crawled);
+    }
+
+    if args.fetch_scripts && !state::interrupted() {
+        let config = javascript::ScriptConfig {
+            third_party: args.third_party_scripts,
+            source_maps: args.source_maps,
+            max_scripts: args.max_scripts,
+            max_size: args.max_resource_size,
+            concurrency,
+            robots: if respect_robots { robots_group.clone() } else { None },
+        };
+        let origin = Url::parse(&base_url)?;
+        let scripts_fetched = javascript::fetch_scripts(&client, &origin, linked_scripts, &config, |file| match file {
+            javascript::ScriptFile::Script { url, record, body } => {
+                report.record_probe(record);
+                match body {
+                    Some(body) => save_script_and_scan(&mut report, &paths.resource_dir, &url, "application/javascript", &body),
+                    None => Ok(()),
+                }
+            }
+            javascript::ScriptFile::SourceMap { url, record, body } => {
+                if let Some(record) = record {
+                    report.record_probe(record);
+                }
+                let Some(body) = body else {
+                    return Ok(());
+                };
+                save_script_and_scan(&mut report, &paths.resource_dir, &url, "application/json", &body)?;
+                unpack_source_map_and_scan(&mut report, &paths.resource_dir, &url, &body)
+            }
+        })
+        .await;
+        println!("Script analysis {}, {} scripts fetched.", phase_outcome(), scripts_fetched);
+    }
+
+    let failures = report.failures();
+    if !failures.is_empty() {
+        let failed: usize = failures.values().sum();
+        let reasons: Vec<String> = failures.iter().map(|(class, count)| format!("{}: {}", class, count)).collect();
+        println!("{} requests failed without a response ({}).", failed, reasons.join(", "));
+    }
+
+    headers::write_header_log(&paths.html_dir, &report.probes)?;
+    report.write_redirect_intel(&paths.intel_dir)?;
+
+    if let Some(json_path) = &args.json {
+        report.write_json(json_path)?;
+    }
+    if let Some(csv_dir) = &args.csv {
+        report.write_csv(csv_dir)?;
+    }
+
+    //an interrupted run stays resumable, even when the interruption came after the scan loop
+    if !state::interrupted() {
+        scan_state.finished = true;
+        scan_state.save(&paths.run_dir)?;
+    }
 
     Ok(())
 }
 
 
-fn get_user_input() -> String {
+//returns None when stdin is closed or unreadable
+fn get_user_input() -> Option<String> {
     let mut input = String::new();
-    io::stdin().read_line(&mut input).unwrap();
-    input.trim().to_string()
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(input.trim().to_string()),
+    }
 }
 
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = Args::parse();
+    scanner::set_quiet(args.quiet);
 
     // Basis-URL-Validierung
     if !args.url.starts_with("http://") && !args.url.starts_with("https://") {
@@ -314,16 +1504,77 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(1);
     }
 
+    let specs = wordlist::parse_wordlist_specs(&args.wordlists);
+    if specs.iter().filter(|spec| spec.is_stdin()).count() > 1 {
+        eprintln!("Error: only one wordlist can be read from stdin");
+        std::process::exit(1);
+    }
+    if args.recursive && specs.iter().any(wordlist::WordlistSpec::is_stdin) {
+        eprintln!("Error: --recursive reads the wordlist once per directory, it cannot come from stdin");
+        std::process::exit(1);
+    }
+
+    //placeholders in the URL turn everything from their path segment on into the path template
+    match wordlist::split_template(&args.url, &specs) {
+        Ok((url, template)) => {
+            args.url = url;
+            args.path_template = template;
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    if args.rate.is_some_and(|rate| !(rate > 0.0 && rate.is_finite())) {
+        eprintln!("Error: --rate must be a positive number of requests per second");
+        std::process::exit(1);
+    }
+
+    match technologies::load_signatures(args.signatures.as_deref()) {
+        Ok(count) => println!("Loaded {} technology signatures", count),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let target = Url::parse(&args.url)?;
+    let resume_dir = if args.resume {
+        let found = state::find_resumable_run(&args.output_dir, &target);
+        if found.is_none() {
+            println!(
+                "No unfinished run of {} found in '{}', starting a new one.",
+                args.url,
+                args.output_dir.display()
+            );
+        }
+        found
+    } else {
+        None
+    };
+    let resume = resume_dir.is_some();
+    let paths = match resume_dir {
+        Some(run_dir) => OutputPaths::in_dir(run_dir),
+        None => OutputPaths::for_target(&args.output_dir, &target, Utc::now()),
+    };
+    state::install_interrupt_handler();
+
     // --- Primärer Scan-Prozess ---
-    match download_and_save(args) {
+    match download_and_save(args, &paths, resume).await {
         Ok(()) => {
             println!("\n--- Scanning Phase Finished ---");
 
-            println!("Creating intelligence summary in '{}' directory...", INTEL_DIR_DISPLAY);
-            match scanner::scan_all_html_files() {
-                Ok(()) => println!("Intelligence summary created successfully in '{}'.", INTEL_DIR_DISPLAY),
+            //an interrupted run still gets a summary of everything saved so far
+            println!("Creating intelligence summary in '{}' directory...", paths.intel_dir.display());
+            match scanner::scan_all_html_files(&paths.html_dir, &paths.resource_dir, &paths.intel_dir) {
+                Ok(()) => println!("Intelligence summary created successfully in '{}'.", paths.intel_dir.display()),
                 Err(e) => eprintln!("Error creating intelligence summary: {}", e),
             }
+            if state::interrupted() {
+                println!("The run was interrupted, continue it with --resume.");
+                std::process::exit(130);
+            }
         }
         Err(e) => {
             eprintln!("An unrecoverable error occurred during scanning: {}", e);
@@ -0,0 +1,48 @@
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use url::Url;
+
+//directory layout of a single run: <output_dir>/<host>/<timestamp>/{found_html,found_resources,intel}
+#[derive(Debug, Clone)]
+pub struct OutputPaths {
+    pub run_dir: PathBuf,
+    pub html_dir: PathBuf,
+    pub resource_dir: PathBuf,
+    pub intel_dir: PathBuf,
+}
+
+//"example.com" or "example.com_8080", safe to use as a directory name
+pub fn target_dir_name(target: &Url) -> String {
+    let host = target.host_str().unwrap_or("unknown-host");
+    let name = match target.port() {
+        Some(port) => format!("{}_{}", host, port),
+        None => host.to_string(),
+    };
+    name.replace([':', '[', ']', '/', '\\'], "_")
+}
+
+impl OutputPaths {
+    pub fn in_dir(run_dir: PathBuf) -> Self {
+        OutputPaths {
+            html_dir: run_dir.join("found_html"),
+            resource_dir: run_dir.join("found_resources"),
+            intel_dir: run_dir.join("intel"),
+            run_dir,
+        }
+    }
+
+    //a fresh run directory namespaced by target host and start time
+    pub fn for_target(output_dir: &Path, target: &Url, started: DateTime<Utc>) -> Self {
+        let run_dir = output_dir
+            .join(target_dir_name(target))
+            .join(started.format("%Y-%m-%dT%H-%M-%S").to_string());
+        OutputPaths::in_dir(run_dir)
+    }
+
+    pub fn create(&self) -> Result<(), std::io::Error> {
+        create_dir_all(&self.html_dir)?;
+        create_dir_all(&self.resource_dir)?;
+        create_dir_all(&self.intel_dir)
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::{HashSet, VecDeque};
+use regex::Regex;
+use crate::listing::is_directory_listing;
+
+#[derive(Debug, Clone)]
+pub struct RecursionConfig {
+    //how many directory levels below a hit are brute-forced
+    pub max_depth: usize,
+    //directories queued from the hits of one branch
+    pub max_dirs: usize,
+    //hits after which a branch is abandoned, it most likely answers every path
+    pub max_hits: usize,
+    //directories matching one of these are not entered
+    pub exclude: Vec<Regex>,
+}
+
+//a directory whose paths are brute-forced with the wordlist, the path ends with a slash
+#[derive(Debug, Clone)]
+pub struct Branch {
+    pub path: String,
+    pub depth: usize,
+}
+
+//why a directory hit is not queued
+pub enum Skip {
+    Depth,
+    Excluded,
+    BranchLimit,
+}
+
+//Directories found during the scan, brute-forced breadth first once the current branch is done.
+//The scan of the target itself is the branch "/" at depth 0.
+pub struct Recursion {
+    config: RecursionConfig,
+    queue: VecDeque<Branch>,
+    queued: HashSet<String>,
+    current: Branch,
+    dirs_in_branch: usize,
+    hits_in_branch: usize,
+}
+
+impl Recursion {
+    pub fn new(config: RecursionConfig) -> Self {
+        Recursion {
+            config,
+            queue: VecDeque::new(),
+            queued: HashSet::from(["/".to_string()]),
+            current: Branch {
+                path: "/".to_string(),
+                depth: 0,
+            },
+            dirs_in_branch: 0,
+            hits_in_branch: 0,
+        }
+    }
+
+    pub fn current(&self) -> &Branch {
+        &self.current
+    }
+
+    //Queues a directory found in the current branch; Ok(false) when it was queued before.
+    pub fn queue(&mut self, path: &str) -> Result<bool, Skip> {
+        let depth = self.current.depth + 1;
+        if self.queued.contains(path) {
+            return Ok(false);
+        }
+        if depth > self.config.max_depth {
+            return Err(Skip::Depth);
+        }
+        if self.config.exclude.iter().any(|pattern| pattern.is_match(path)) {
+            return Err(Skip::Excluded);
+        }
+        if self.dirs_in_branch >= self.config.max_dirs {
+            return Err(Skip::BranchLimit);
+        }
+        self.dirs_in_branch += 1;
+        self.requeue(path, depth);
+        Ok(true)
+    }
+
+    //puts back a directory found before an interruption, with the depth it was found at
+    pub fn requeue(&mut self, path: &str, depth: usize) {
+        if self.queued.insert(path.to_string()) {
+            self.queue.push_back(Branch {
+                path: path.to_string(),
+                depth,
+            });
+        }
+    }
+
+    //Counts a hit in the current branch, false once the branch has more hits than allowed.
+    //The target itself has no limit.
+    pub fn hit(&mut self) -> bool {
+        self.hits_in_branch += 1;
+        self.current.depth == 0 || self.hits_in_branch <= self.config.max_hits
+    }
+
+    pub fn next_branch(&mut self) -> Option<Branch> {
+        let branch = self.queue.pop_front()?;
+        self.current = branch.clone();
+        self.dirs_in_branch = 0;
+        self.hits_in_branch = 0;
+        Some(branch)
+    }
+}
+
+//The directory a hit stands for, with a trailing slash: the probed path ends with a slash,
+//it redirected to itself with a slash appended (`landing`), or it shows a directory listing.
+pub fn directory_of(path: &str, landing: Option<&str>, body: Option<&str>) -> Option<String> {
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    if path.ends_with('/') {
+        return (path != "/").then(|| path.to_string());
+    }
+    let with_slash = format!("{}/", path);
+    if landing == Some(with_slash.as_str()) || body.is_some_and(is_directory_listing) {
+        return Some(with_slash);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recursion(max_depth: usize, max_dirs: usize, max_hits: usize) -> Recursion {
+        Recursion::new(RecursionConfig {
+            max_depth,
+            max_dirs,
+            max_hits,
+            exclude: vec![Regex::new("^/static/").unwrap()],
+        })
+    }
+
+    #[test]
+    fn directories_are_recognised_by_slash_redirect_or_listing() {
+        assert_eq!(directory_of("/admin/", None, None).as_deref(), Some("/admin/"));
+        assert_eq!(directory_of("/admin/?sort=name", None, None).as_deref(), Some("/admin/"));
+        assert_eq!(directory_of("/docs", Some("/docs/"), None).as_deref(), Some("/docs/"));
+        let listing = "<html><head><title>Index of /files</title></head><body><h1>Index of /files</h1></body></html>";
+        assert_eq!(directory_of("/files", None, Some(listing)).as_deref(), Some("/files/"));
+
+        assert_eq!(directory_of("/", None, None), None);
+        assert_eq!(directory_of("/login", Some("/login/form"), Some("<html>Sign in</html>")), None);
+    }
+
+    #[test]
+    fn directories_are_queued_once_and_entered_breadth_first() {
+        let mut recursion = recursion(3, 10, 10);
+        assert!(matches!(recursion.queue("/admin/"), Ok(true)));
+        assert!(matches!(recursion.queue("/backup/"), Ok(true)));
+        assert!(matches!(recursion.queue("/admin/"), Ok(false)));
+        assert!(matches!(recursion.queue("/"), Ok(false)));
+        assert!(matches!(recursion.queue("/static/js/"), Err(Skip::Excluded)));
+
+        let branch = recursion.next_branch().unwrap();
+        assert_eq!((branch.path.as_str(), branch.depth), ("/admin/", 1));
+        assert!(matches!(recursion.queue("/admin/old/"), Ok(true)));
+        assert_eq!(recursion.next_branch().unwrap().path, "/backup/");
+        let branch = recursion.next_branch().unwrap();
+        assert_eq!((branch.path.as_str(), branch.depth), ("/admin/old/", 2));
+        assert!(recursion.next_branch().is_none());
+    }
+
+    #[test]
+    fn depth_and_branch_limits_are_enforced() {
+        let mut recursion = recursion(1, 2, 10);
+        assert!(matches!(recursion.queue("/a/"), Ok(true)));
+        assert!(matches!(recursion.queue("/b/"), Ok(true)));
+        assert!(matches!(recursion.queue("/c/"), Err(Skip::BranchLimit)));
+
+        //a new branch starts with an empty directory count, but is already at the deepest level
+        recursion.next_branch().unwrap();
+        assert!(matches!(recursion.queue("/a/deeper/"), Err(Skip::Depth)));
+    }
+
+    #[test]
+    fn branches_are_abandoned_after_too_many_hits() {
+        let mut recursion = recursion(2, 10, 2);
+        //the target itself has no limit
+        assert!((0..5).all(|_| recursion.hit()));
+
+        assert!(matches!(recursion.queue("/catch-all/"), Ok(true)));
+        recursion.next_branch().unwrap();
+        assert!(recursion.hit());
+        assert!(recursion.hit());
+        assert!(!recursion.hit());
+    }
+
+    #[test]
+    fn requeued_directories_keep_their_depth() {
+        let mut recursion = recursion(3, 1, 10);
+        recursion.requeue("/a/b/", 2);
+        recursion.requeue("/a/b/", 2);
+        let branch = recursion.next_branch().unwrap();
+        assert_eq!((branch.path.as_str(), branch.depth), ("/a/b/", 2));
+        assert!(recursion.next_branch().is_none());
+    }
+}
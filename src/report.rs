@@ -0,0 +1,488 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{File, OpenOptions, create_dir_all};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+use chrono::{DateTime, Utc};
+use reqwest::Response;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use crate::headers;
+use crate::http::{self, ErrorClass, RedirectHop};
+use crate::scanner::ScannerInfos;
+
+//one request made during the run, successful or not
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProbeRecord {
+    pub url: String,
+    pub status: Option<u16>,
+    pub content_type: Option<String>,
+    pub size: Option<u64>,
+    //final URL when redirects were followed, the Location of a 3xx response that was not followed
+    pub redirect_target: Option<String>,
+    //every redirect followed on the way to the response
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirect_chain: Vec<RedirectHop>,
+    //the redirect leads to another host
+    pub off_site_redirect: bool,
+    pub elapsed_ms: u64,
+    pub error: Option<String>,
+    //what kind of failure `error` is
+    pub error_class: Option<ErrorClass>,
+    //the body matched the calibrated "not found" page
+    pub soft_404: bool,
+    //not matched by --match-codes or dropped by one of the filters
+    pub filtered: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+}
+
+impl ProbeRecord {
+    pub fn from_response(url: &str, resp: &Response, started: Instant) -> Self {
+        let redirect_target = http::redirect_target(resp);
+        ProbeRecord {
+            url: url.to_string(),
+            status: Some(resp.status().as_u16()),
+            content_type: resp
+                .headers()
+                .get("content-type")
+                .and_then(|ct| ct.to_str().ok())
+                .map(String::from),
+            size: resp.content_length(),
+            off_site_redirect: redirect_target.as_deref().is_some_and(|target| http::is_off_site(url, target)),
+            redirect_target,
+            redirect_chain: http::redirect_chain(resp),
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            error: None,
+            error_class: None,
+            soft_404: false,
+            filtered: false,
+            headers: headers::header_pairs(resp.headers()),
+        }
+    }
+
+    pub fn from_error(url: &str, error: &reqwest::Error, started: Instant) -> Self {
+        ProbeRecord {
+            url: url.to_string(),
+            status: None,
+            content_type: None,
+            size: None,
+            redirect_target: None,
+            redirect_chain: Vec::new(),
+            off_site_redirect: false,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            error: Some(error.to_string()),
+            error_class: Some(ErrorClass::classify(error)),
+            soft_404: false,
+            filtered: false,
+            headers: Vec::new(),
+        }
+    }
+
+    //"http://host/admin -> 302 -> http://host/login", marked " [loop]" when the chain came back
+    //to a URL it had already visited and " [off-site]" when it leads to another host
+    pub fn redirect_summary(&self) -> Option<String> {
+        let target = self.redirect_target.as_ref()?;
+        let mut summary = self.url.clone();
+        for hop in &self.redirect_chain {
+            summary.push_str(&format!(" -> {} -> {}", hop.status, hop.location));
+        }
+        //the last response is a redirect that was not followed
+        if let Some(status) = self.status.filter(|status| (300..400).contains(status)) {
+            summary.push_str(&format!(" -> {} -> {}", status, target));
+            if target == &self.url || self.redirect_chain.iter().any(|hop| &hop.url == target) {
+                summary.push_str(" [loop]");
+            }
+        }
+        if self.off_site_redirect {
+            summary.push_str(" [off-site]");
+        }
+        Some(summary)
+    }
+
+    //a response that was not dropped by the soft-404 check or the filters; which statuses
+    //count is up to the filters, a 404 kept through --match-codes is a response like any other
+    pub fn is_kept(&self) -> bool {
+        self.status.is_some() && !self.soft_404 && !self.filtered
+    }
+
+    //for a response whose body could not be read
+    pub fn set_error(&mut self, error: &reqwest::Error) {
+        self.error = Some(error.to_string());
+        self.error_class = Some(ErrorClass::classify(error));
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct FindingRecord {
+    pub occurrences: usize,
+    pub sources: BTreeSet<String>,
+    //first line the value appears on, per source page
+    pub lines: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize)]
+struct ProbeCsvRow<'a> {
+    url: &'a str,
+    status: Option<u16>,
+    content_type: Option<&'a str>,
+    length: Option<u64>,
+    redirect_target: Option<&'a str>,
+    redirect_hops: usize,
+    off_site_redirect: bool,
+    elapsed_ms: u64,
+    soft_404: bool,
+    error_class: Option<ErrorClass>,
+}
+
+#[derive(Serialize)]
+struct FindingCsvRow<'a> {
+    category: &'a str,
+    value: &'a str,
+    source_url: &'a str,
+    line: Option<usize>,
+}
+
+//1-based line of the first occurrence of `value` in `content`; meta tags are
+//stored as "name: content", so their content part is searched as a fallback
+fn find_line(content: &str, value: &str) -> Option<usize> {
+    let lowered = content.to_ascii_lowercase();
+    let position = content
+        .find(value)
+        .or_else(|| lowered.find(&value.to_ascii_lowercase()))
+        .or_else(|| {
+            value
+                .split_once(": ")
+                .and_then(|(_, meta_content)| content.find(meta_content))
+        })?;
+    Some(content[..position].matches('\n').count() + 1)
+}
+
+//the probes and page findings of a run, one JSON line each, appended as they are recorded
+//so that a resumed run reports everything found before the interruption as well
+pub const REPORT_LOG_FILE: &str = "report.jsonl";
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum ReportEntry {
+    Probe(ProbeRecord),
+    Findings {
+        source_url: String,
+        //category, value and the line it first appears on
+        lines: Vec<(String, String, usize)>,
+        info: Box<ScannerInfos>,
+    },
+}
+
+//collects probes and per-page findings while the scan runs, written with --json
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub target: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub probes: Vec<ProbeRecord>,
+    //category -> finding value -> where and how often it was seen
+    pub findings: BTreeMap<&'static str, BTreeMap<String, FindingRecord>>,
+    pub summary: ScannerInfos,
+    #[serde(skip)]
+    log: Option<BufWriter<File>>,
+}
+
+impl Report {
+    pub fn new(target: &str) -> Self {
+        Report {
+            target: target.to_string(),
+            started_at: Utc::now(),
+            finished_at: None,
+            probes: Vec::new(),
+            findings: BTreeMap::new(),
+            summary: ScannerInfos::default(),
+            log: None,
+        }
+    }
+
+    //A report kept in the report log of `run_dir`: what an interrupted run recorded there is
+    //loaded again (a line cut off by a kill is ignored), everything recorded from now on is appended.
+    pub fn open(target: &str, run_dir: &Path) -> Result<Self, std::io::Error> {
+        let mut report = Report::new(target);
+        let path = run_dir.join(REPORT_LOG_FILE);
+        if path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                if let Ok(entry) = serde_json::from_str::<ReportEntry>(&line?) {
+                    report.apply(entry);
+                }
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        report.log = Some(BufWriter::new(file));
+        Ok(report)
+    }
+
+    //Header fingerprints are attributed to the host, redirects to the probed URL like any page
+    //finding. A probe that matched the soft-404 baseline or was filtered out says nothing
+    //about the site, its headers are dropped and its redirect is not reported.
+    pub fn record_probe(&mut self, mut probe: ProbeRecord) {
+        if !probe.is_kept() {
+            probe.headers = Vec::new();
+        }
+        self.add(ReportEntry::Probe(probe));
+    }
+
+    //attributes every finding of a scanned page to the page URL
+    pub fn record_findings(&mut self, source_url: &str, content: &str, info: ScannerInfos) {
+        let mut lines = Vec::new();
+        for (category, values) in info.findings() {
+            for value in values {
+                //secrets are stored as "Rule: value", the raw value is what appears in the page
+                let needle = match info.secret_details.get(value.as_str()) {
+                    Some(secret) if category == "secrets" => &secret.value,
+                    _ => value,
+                };
+                if let Some(line) = find_line(content, needle) {
+                    lines.push((category.to_string(), value.clone(), line));
+                }
+            }
+        }
+        self.add(ReportEntry::Findings {
+            source_url: source_url.to_string(),
+            lines,
+            info: Box::new(info),
+        });
+    }
+
+    //flushed per entry like the progress log, a killed run loses at most the entry in flight
+    fn add(&mut self, entry: ReportEntry) {
+        if let Some(log) = &mut self.log {
+            let written = serde_json::to_writer(&mut *log, &entry)
+                .map_err(std::io::Error::from)
+                .and_then(|()| writeln!(log))
+                .and_then(|()| log.flush());
+            if let Err(e) = written {
+                eprintln!("Warning: could not append to {}: {}", REPORT_LOG_FILE, e);
+            }
+        }
+        self.apply(entry);
+    }
+
+    fn apply(&mut self, entry: ReportEntry) {
+        match entry {
+            ReportEntry::Probe(probe) => {
+                if probe.is_kept() {
+                    //every response of a host repeats its server headers, one source per host is enough
+                    let host = Url::parse(&probe.url)
+                        .map(|url| url.origin().ascii_serialization())
+                        .unwrap_or_else(|_| probe.url.clone());
+                    self.apply_findings(&host, &[], headers::fingerprint_headers(&probe.headers));
+                    if let Some(summary) = probe.redirect_summary() {
+                        let mut redirect_info = ScannerInfos::default();
+                        redirect_info.count("redirects", &summary);
+                        redirect_info.redirects.insert(summary);
+                        self.apply_findings(&probe.url, &[], redirect_info);
+                    }
+                }
+                self.probes.push(probe);
+            }
+            ReportEntry::Findings { source_url, lines, info } => self.apply_findings(&source_url, &lines, *info),
+        }
+    }
+
+    fn apply_findings(&mut self, source_url: &str, lines: &[(String, String, usize)], info: ScannerInfos) {
+        for (category, values) in info.findings() {
+            let findings = self.findings.entry(category).or_default();
+            for value in values {
+                let finding = findings.entry(value.clone()).or_default();
+                finding.occurrences += info.occurrences_of(category, value);
+                finding.sources.insert(source_url.to_string());
+            }
+        }
+        for (category, value, line) in lines {
+            if let Some(finding) = self
+                .findings
+                .get_mut(category.as_str())
+                .and_then(|findings| findings.get_mut(value))
+            {
+                finding.lines.insert(source_url.to_string(), *line);
+            }
+        }
+        self.summary.merge(info);
+    }
+
+    //number of failed requests per error class
+    pub fn failures(&self) -> BTreeMap<ErrorClass, usize> {
+        let mut failures = BTreeMap::new();
+        for class in self.probes.iter().filter_map(|probe| probe.error_class) {
+            *failures.entry(class).or_insert(0) += 1;
+        }
+        failures
+    }
+
+    //intel/redirects.txt, off-site redirects first
+    pub fn write_redirect_intel(&self, intel_dir_path: &Path) -> Result<(), std::io::Error> {
+        let Some(redirects) = self.findings.get("redirects") else {
+            return Ok(());
+        };
+        create_dir_all(intel_dir_path)?;
+
+        let path = intel_dir_path.join("redirects.txt");
+        let mut writer = BufWriter::new(File::create(&path)?);
+        let (off_site, on_site): (Vec<&String>, Vec<&String>) =
+            redirects.keys().partition(|summary| summary.ends_with("[off-site]"));
+        for summary in off_site.iter().chain(&on_site) {
+            writeln!(writer, "{}", summary)?;
+        }
+        writer.flush()?;
+        println!(
+            "Intel summary written to {} ({} items, {} off-site)",
+            path.display(),
+            redirects.len(),
+            off_site.len()
+        );
+        Ok(())
+    }
+
+    pub fn write_json(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        self.finished_at = Some(Utc::now());
+
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            create_dir_all(parent)?;
+        }
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        println!("JSON report written to {} ({} probes)", path.display(), self.probes.len());
+        Ok(())
+    }
+
+    //writes probes.csv and findings.csv into `dir`
+    pub fn write_csv(&self, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        create_dir_all(dir)?;
+
+        let probes_path = dir.join("probes.csv");
+        let mut writer = csv::Writer::from_path(&probes_path)?;
+        for probe in &self.probes {
+            writer.serialize(ProbeCsvRow {
+                url: &probe.url,
+                status: probe.status,
+                content_type: probe.content_type.as_deref(),
+                length: probe.size,
+                redirect_target: probe.redirect_target.as_deref(),
+                redirect_hops: probe.redirect_chain.len(),
+                off_site_redirect: probe.off_site_redirect,
+                elapsed_ms: probe.elapsed_ms,
+                soft_404: probe.soft_404,
+                error_class: probe.error_class,
+            })?;
+        }
+        writer.flush()?;
+        println!("CSV written to {} ({} rows)", probes_path.display(), self.probes.len());
+
+        let findings_path = dir.join("findings.csv");
+        let mut writer = csv::Writer::from_path(&findings_path)?;
+        let mut rows = 0;
+        for (category, findings) in &self.findings {
+            for (value, finding) in findings {
+                for source_url in &finding.sources {
+                    writer.serialize(FindingCsvRow {
+                        category,
+                        value,
+                        source_url,
+                        line: finding.lines.get(source_url).copied(),
+                    })?;
+                    rows += 1;
+                }
+            }
+        }
+        writer.flush()?;
+        println!("CSV written to {} ({} rows)", findings_path.display(), rows);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(url: &str, status: u16, redirect_target: Option<&str>, redirect_chain: Vec<RedirectHop>) -> ProbeRecord {
+        ProbeRecord {
+            url: url.to_string(),
+            status: Some(status),
+            content_type: None,
+            size: None,
+            redirect_target: redirect_target.map(str::to_string),
+            redirect_chain,
+            off_site_redirect: false,
+            elapsed_ms: 0,
+            error: None,
+            error_class: None,
+            soft_404: false,
+            filtered: false,
+            headers: Vec::new(),
+        }
+    }
+
+    fn hop(url: &str, status: u16, location: &str) -> RedirectHop {
+        RedirectHop {
+            url: url.to_string(),
+            status,
+            location: location.to_string(),
+        }
+    }
+
+    #[test]
+    fn no_summary_without_a_redirect() {
+        assert_eq!(probe("http://a.test/x", 200, None, Vec::new()).redirect_summary(), None);
+    }
+
+    #[test]
+    fn followed_redirects_list_every_hop() {
+        let record = probe(
+            "http://a.test/old",
+            200,
+            Some("http://a.test/new/"),
+            vec![hop("http://a.test/old", 301, "http://a.test/new"), hop("http://a.test/new", 301, "http://a.test/new/")],
+        );
+        assert_eq!(
+            record.redirect_summary().unwrap(),
+            "http://a.test/old -> 301 -> http://a.test/new -> 301 -> http://a.test/new/"
+        );
+    }
+
+    #[test]
+    fn an_unfollowed_redirect_ends_the_chain() {
+        let record = probe("http://a.test/admin", 302, Some("http://a.test/login"), Vec::new());
+        assert_eq!(record.redirect_summary().unwrap(), "http://a.test/admin -> 302 -> http://a.test/login");
+    }
+
+    #[test]
+    fn loops_and_off_site_targets_are_marked() {
+        let record = probe(
+            "http://a.test/a",
+            302,
+            Some("http://a.test/a"),
+            vec![hop("http://a.test/a", 302, "http://a.test/b")],
+        );
+        assert_eq!(
+            record.redirect_summary().unwrap(),
+            "http://a.test/a -> 302 -> http://a.test/b -> 302 -> http://a.test/a [loop]"
+        );
+
+        let mut record = probe("http://a.test/out", 301, Some("https://b.test/"), Vec::new());
+        record.off_site_redirect = true;
+        assert_eq!(record.redirect_summary().unwrap(), "http://a.test/out -> 301 -> https://b.test/ [off-site]");
+    }
+
+    #[test]
+    fn only_unfiltered_responses_are_kept() {
+        let mut record = probe("http://a.test/x", 200, None, Vec::new());
+        assert!(record.is_kept());
+        record.soft_404 = true;
+        assert!(!record.is_kept());
+        //statuses are left to the filters
+        let mut not_found = probe("http://a.test/x", 404, None, Vec::new());
+        assert!(not_found.is_kept());
+        not_found.filtered = true;
+        assert!(!not_found.is_kept());
+        assert!(!ProbeRecord { status: None, ..probe("http://a.test/x", 200, None, Vec::new()) }.is_kept());
+    }
+}
@@ -0,0 +1,284 @@
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+use regex::Regex;
+use serde_json::Value;
+use crate::scanner::{self, ScannerInfos};
+use crate::{javascript, secrets};
+
+//content-aware scanner selection for non-HTML responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+    Json,
+    Xml,
+    JavaScript,
+    Text,
+    Binary,
+}
+
+//key names whose values are worth reporting, matched case-insensitively as substrings
+const SENSITIVE_KEYS: &[&str] = &[
+    "password", "passwd", "pwd", "secret", "token", "api_key", "apikey", "api-key",
+    "access_key", "accesskey", "private_key", "authorization", "credential", "connectionstring",
+    "connection_string", "database_url", "db_pass", "db_user", "dsn",
+];
+
+static URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"https?://[^\s"'<>`)\]}]+"#).unwrap());
+//KEY=value, KEY: value, export KEY=value, set KEY=value
+static TEXT_ASSIGNMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^\s*(?:export\s+|set\s+)?([A-Za-z0-9_.\-]+)\s*[:=]\s*["']?([^"'#\r\n]*)["']?"#).unwrap()
+});
+//PHP constants as used by wp-config.php: define('DB_PASSWORD', 'value');
+static PHP_DEFINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"define\(\s*["']([^"']+)["']\s*,\s*["']([^"']*)["']"#).unwrap());
+//<password>value</password>
+static XML_ELEMENT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<([\w:.\-]+)[^>]*>([^<]+)</([\w:.\-]+)>").unwrap());
+//connectionString="..."
+static XML_ATTRIBUTE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"([\w:.\-]+)\s*=\s*"([^"]*)""#).unwrap());
+//web.config style <add key="DbPassword" value="..."/>
+static XML_KEY_VALUE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?:key|name)\s*=\s*"([^"]+)"\s+value\s*=\s*"([^"]*)""#).unwrap());
+//quoted absolute paths like "/api/v1/users" or "/static/app.js"
+static JS_PATH_LITERAL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"["'`](/[A-Za-z0-9_\-./{}:]+)["'`]"#).unwrap());
+//apiKey: "value", const SECRET = 'value'
+static JS_ASSIGNMENT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"([A-Za-z_$][\w$]*)["']?\s*[:=]\s*["'`]([^"'`\r\n]+)["'`]"#).unwrap());
+
+const BINARY_EXTENSIONS: &[&str] = &[
+    "bin", "zip", "gz", "tgz", "bz2", "xz", "7z", "rar", "tar", "pdf", "png", "jpg", "jpeg",
+    "gif", "ico", "webp", "woff", "woff2", "ttf", "eot", "mp3", "mp4", "exe", "dll", "so", "jar",
+    "war", "class", "db", "sqlite",
+];
+
+impl ResourceKind {
+    pub fn from_extension(extension: &str) -> Self {
+        match extension.to_ascii_lowercase().as_str() {
+            "json" | "map" | "webmanifest" => ResourceKind::Json,
+            "xml" | "rss" | "atom" | "svg" | "xsd" | "wsdl" => ResourceKind::Xml,
+            "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" | "mts" | "cts" | "vue" | "svelte" => ResourceKind::JavaScript,
+            ext if BINARY_EXTENSIONS.contains(&ext) => ResourceKind::Binary,
+            _ => ResourceKind::Text,
+        }
+    }
+}
+
+fn extension_for_content_type(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    let extension = match mime.as_str() {
+        m if m.ends_with("json") => "json",
+        m if m.ends_with("javascript") || m.ends_with("ecmascript") => "js",
+        m if m.ends_with("xml") => "xml",
+        "text/css" => "css",
+        "text/plain" => "txt",
+        "text/csv" => "csv",
+        "application/x-yaml" | "application/yaml" | "text/yaml" => "yml",
+        "application/sql" => "sql",
+        "application/pdf" => "pdf",
+        "application/zip" => "zip",
+        "application/gzip" | "application/x-gzip" => "gz",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "application/octet-stream" => "bin",
+        _ => return None,
+    };
+    Some(extension)
+}
+
+//File extension for a saved resource. JSON, XML and JavaScript content types win because
+//they decide which scanner runs; otherwise the requested file's own extension is kept
+//(e.g. ".env", ".sql", ".bak"), falling back to the content type.
+pub fn extension_for(content_type: &str, path: &str) -> String {
+    let from_content_type = extension_for_content_type(content_type);
+    if let Some(extension @ ("json" | "xml" | "js")) = from_content_type {
+        return extension.to_string();
+    }
+
+    let file_name = path.split(['?', '#']).next().unwrap_or("").rsplit('/').next().unwrap_or("");
+    if let Some((_, extension)) = file_name.rsplit_once('.')
+        && !extension.is_empty()
+        && extension.len() <= 8
+        && extension.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        return extension.to_ascii_lowercase();
+    }
+
+    from_content_type.unwrap_or("bin").to_string()
+}
+
+fn is_sensitive_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    SENSITIVE_KEYS.iter().any(|sensitive| key.contains(sensitive))
+}
+
+fn record_secret(info: &mut ScannerInfos, key: &str, value: &str) {
+    let value = value.trim();
+    //values already matched by a specific secrets rule are not reported twice
+    if value.is_empty()
+        || !is_sensitive_key(key)
+        || info.secret_details.values().any(|known| known.value == value)
+    {
+        return;
+    }
+    //the location is filled in by secrets::locate once the whole resource is scanned
+    secrets::record(info, "Sensitive assignment", Some(key), value, None);
+}
+
+fn record_link(info: &mut ScannerInfos, url: &str) {
+    let url = url.trim_end_matches(['.', ',', ';']);
+    info.count("links", url);
+    info.links.insert(url.to_string());
+}
+
+fn extract_urls(content: &str, info: &mut ScannerInfos) {
+    for url in URL.find_iter(content) {
+        record_link(info, url.as_str());
+    }
+}
+
+//.env files, SQL dumps, YAML and ini style configs
+fn scan_text(content: &str, info: &mut ScannerInfos) {
+    for line in content.lines() {
+        if let Some(cap) = TEXT_ASSIGNMENT.captures(line) {
+            record_secret(info, &cap[1], &cap[2]);
+        }
+        for cap in PHP_DEFINE.captures_iter(line) {
+            record_secret(info, &cap[1], &cap[2]);
+        }
+    }
+    extract_urls(content, info);
+}
+
+fn walk_json(value: &Value, key: Option<&str>, info: &mut ScannerInfos) {
+    match value {
+        Value::Object(map) => {
+            for (child_key, child) in map {
+                walk_json(child, Some(child_key), info);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                walk_json(item, key, info);
+            }
+        }
+        Value::String(text) => {
+            if let Some(key) = key {
+                record_secret(info, key, text);
+            }
+            if text.starts_with("http://") || text.starts_with("https://") {
+                record_link(info, text);
+            } else if text.starts_with('/') && text.len() > 1 && !text.contains(char::is_whitespace) {
+                info.count("api_endpoints", text);
+                info.api_endpoints.insert(text.to_string());
+            }
+        }
+        Value::Number(number) => {
+            if let Some(key) = key {
+                record_secret(info, key, &number.to_string());
+            }
+        }
+        _ => {}
+    }
+}
+
+fn scan_xml(content: &str, info: &mut ScannerInfos) {
+    for cap in XML_ELEMENT.captures_iter(content) {
+        if cap[1] == cap[3] {
+            record_secret(info, &cap[1], &cap[2]);
+        }
+    }
+
+    for cap in XML_ATTRIBUTE.captures_iter(content) {
+        record_secret(info, &cap[1], &cap[2]);
+    }
+    for cap in XML_KEY_VALUE.captures_iter(content) {
+        record_secret(info, &cap[1], &cap[2]);
+    }
+
+    extract_urls(content, info);
+}
+
+fn scan_javascript(content: &str, info: &mut ScannerInfos) {
+    for cap in JS_PATH_LITERAL.captures_iter(content) {
+        let path = &cap[1];
+        if path.len() > 1 && !path.starts_with("//") {
+            info.count("api_endpoints", path);
+            info.api_endpoints.insert(path.to_string());
+        }
+    }
+
+    for cap in JS_ASSIGNMENT.captures_iter(content) {
+        record_secret(info, &cap[1], &cap[2]);
+    }
+
+    javascript::extract_endpoints(content, info);
+    extract_urls(content, info);
+}
+
+//`source` is the saved file, it is used as the location of secret findings
+pub fn scan_resource(content: &str, kind: ResourceKind, source: &str) -> Result<ScannerInfos, Box<dyn std::error::Error>> {
+    let mut info = ScannerInfos::default();
+    if kind == ResourceKind::Binary {
+        return Ok(info);
+    }
+
+    secrets::scan_secrets(content, source, &mut info);
+    match kind {
+        ResourceKind::Json => match serde_json::from_str::<Value>(content) {
+            Ok(value) => walk_json(&value, None, &mut info),
+            Err(_) => scan_text(content, &mut info),
+        },
+        ResourceKind::Xml => scan_xml(content, &mut info),
+        ResourceKind::JavaScript => scan_javascript(content, &mut info),
+        ResourceKind::Text => scan_text(content, &mut info),
+        ResourceKind::Binary => {}
+    }
+
+    secrets::locate(content, source, &mut info);
+    scanner::extract_emails(content, &mut info)?;
+    scanner::extract_api_endpoints(content, &mut info)?;
+    Ok(info)
+}
+
+//scans a saved resource, the scanner is picked from the file extension
+pub fn scan_resource_file(path: &Path) -> Result<ScannerInfos, Box<dyn std::error::Error>> {
+    let kind = ResourceKind::from_extension(
+        &path.extension().map(|ext| ext.to_string_lossy()).unwrap_or_default(),
+    );
+    if kind == ResourceKind::Binary {
+        return Ok(ScannerInfos::default());
+    }
+
+    let bytes = fs::read(path)?;
+    scan_resource(&String::from_utf8_lossy(&bytes), kind, &path.to_string_lossy())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scanner_content_types_decide_the_extension() {
+        assert_eq!(extension_for("application/json; charset=utf-8", "/api/users"), "json");
+        assert_eq!(extension_for("application/javascript", "/static/app.min"), "js");
+        assert_eq!(extension_for("text/xml", "/feed.rss"), "xml");
+    }
+
+    #[test]
+    fn other_resources_keep_their_own_extension() {
+        assert_eq!(extension_for("application/octet-stream", "/backup/db.SQL"), "sql");
+        assert_eq!(extension_for("text/plain", "/.env"), "env");
+        assert_eq!(extension_for("text/plain", "/download.php?file=a.txt"), "php");
+        //no usable extension of its own, the content type is the fallback
+        assert_eq!(extension_for("text/plain", "/notes"), "txt");
+        assert_eq!(extension_for("application/x-unknown", "/archive.tar-v2~"), "bin");
+    }
+
+    #[test]
+    fn text_resources_report_sensitive_assignments() {
+        let info = scan_resource("DB_PASSWORD=hunter2\nAPP_NAME=shop\nSEE=https://example.com/docs\n", ResourceKind::Text, ".env").unwrap();
+        assert!(info.secrets.iter().any(|label| label.ends_with("DB_PASSWORD=hunter2")));
+        assert!(!info.secrets.iter().any(|label| label.contains("APP_NAME")));
+        assert!(info.links.contains("https://example.com/docs"));
+    }
+}
@@ -0,0 +1,251 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use clap::ValueEnum;
+use regex::Regex;
+use robots_txt::Robots;
+
+//how robots.txt is used during the scan
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum RobotsMode {
+    /// Probe every path mentioned in robots.txt (default)
+    Mine,
+    /// Skip disallowed paths and honour Crawl-delay
+    Respect,
+}
+
+#[derive(Clone, Debug)]
+pub struct RobotsRule {
+    pub allow: bool,
+    pub pattern: String,
+    matcher: Regex,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RobotsGroup {
+    pub user_agents: Vec<String>,
+    pub rules: Vec<RobotsRule>,
+    pub crawl_delay: Option<f64>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RobotsInfo {
+    pub groups: Vec<RobotsGroup>,
+    pub sitemaps: Vec<String>,
+    pub host: Option<String>,
+}
+
+impl RobotsRule {
+    fn new(allow: bool, pattern: &str) -> Self {
+        //'*' matches any sequence, a trailing '$' anchors the pattern at the end of the path
+        let (body, anchored) = match pattern.strip_suffix('$') {
+            Some(body) => (body, true),
+            None => (pattern, false),
+        };
+        let mut expression = String::from("^");
+        expression.push_str(
+            &body
+                .split('*')
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join(".*"),
+        );
+        if anchored {
+            expression.push('$');
+        }
+
+        RobotsRule {
+            allow,
+            pattern: pattern.to_string(),
+            matcher: Regex::new(&expression).expect("escaped robots pattern is a valid regex"),
+        }
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        self.matcher.is_match(path)
+    }
+}
+
+impl RobotsGroup {
+    //RFC 9309: the longest matching rule wins, allow wins a tie, no match means allowed
+    pub fn is_allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| !rule.pattern.is_empty() && rule.matches(path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+            .filter(|delay| delay.is_finite() && *delay > 0.0)
+            .map(Duration::from_secs_f64)
+    }
+}
+
+impl RobotsInfo {
+    pub fn parse(content: &str) -> Self {
+        let robots = Robots::from_str_lossy(content);
+
+        let mut info = RobotsInfo {
+            host: robots.host.as_ref().map(|host| host.to_string()),
+            ..Default::default()
+        };
+
+        for section in robots.sections.iter().chain(std::iter::once(&robots.default_section)) {
+            info.groups.push(RobotsGroup {
+                user_agents: section.useragents.iter().map(|ua| ua.to_string()).collect(),
+                rules: section
+                    .rules
+                    .iter()
+                    .map(|rule| RobotsRule::new(rule.allow, rule.path.trim()))
+                    .collect(),
+                crawl_delay: section.crawl_delay,
+            });
+            for sitemap in &section.sitemaps {
+                if !info.sitemaps.contains(&sitemap.to_string()) {
+                    info.sitemaps.push(sitemap.to_string());
+                }
+            }
+        }
+
+        info
+    }
+
+    //picks the group whose user-agent token appears in `user_agent`, falling back to '*'
+    pub fn group_for(&self, user_agent: &str) -> Option<&RobotsGroup> {
+        let user_agent = user_agent.to_ascii_lowercase();
+        self.groups
+            .iter()
+            .find(|group| {
+                group.user_agents.iter().any(|ua| {
+                    ua != "*" && !ua.is_empty() && user_agent.contains(&ua.to_ascii_lowercase())
+                })
+            })
+            .or_else(|| {
+                self.groups
+                    .iter()
+                    .find(|group| group.user_agents.iter().any(|ua| ua == "*"))
+            })
+    }
+
+    //every path mentioned in any group, with wildcard patterns expanded into probe candidates
+    pub fn candidate_paths(&self) -> Vec<String> {
+        let mut paths = Vec::new();
+
+        for group in &self.groups {
+            for rule in &group.rules {
+                for candidate in expand_pattern(&rule.pattern) {
+                    if !paths.contains(&candidate) {
+                        paths.push(candidate);
+                    }
+                }
+            }
+        }
+        paths
+    }
+}
+
+//turns a robots pattern like "/admin/*.php$" into concrete paths worth probing
+fn expand_pattern(pattern: &str) -> Vec<String> {
+    let pattern = pattern.trim().trim_end_matches('$');
+    if pattern.is_empty() || pattern == "*" {
+        return vec![];
+    }
+
+    //make sure the path starts with a slash "/"
+    let normalize = |path: &str| {
+        let path = if path.starts_with('/') {
+            path.to_string()
+        } else {
+            format!("/{}", path)
+        };
+        let mut collapsed = String::with_capacity(path.len());
+        for c in path.chars() {
+            if !(c == '/' && collapsed.ends_with('/')) {
+                collapsed.push(c);
+            }
+        }
+        collapsed
+    };
+
+    if !pattern.contains('*') {
+        return vec![normalize(pattern)];
+    }
+
+    let mut candidates = Vec::new();
+
+    //the fixed prefix before the first wildcard, e.g. "/admin/" for "/admin/*.php"
+    let prefix = normalize(pattern.split('*').next().unwrap_or(""));
+    if prefix.len() > 1 {
+        candidates.push(prefix);
+    }
+
+    //the pattern with its wildcards matching nothing, e.g. "/private/" for "/private*/"
+    let collapsed = normalize(&pattern.replace('*', ""));
+    if collapsed.len() > 1 && !candidates.contains(&collapsed) {
+        candidates.push(collapsed);
+    }
+
+    candidates
+}
+
+pub fn load_robots_txt(robots_txt_path: &Path) -> Result<RobotsInfo, Box<dyn std::error::Error>> {
+    if !robots_txt_path.exists() {
+        return Ok(RobotsInfo::default());
+    }
+
+    let content = fs::read_to_string(robots_txt_path)?;
+    Ok(RobotsInfo::parse(&content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "\
+User-agent: Googlebot
+Disallow: /nogoogle/
+
+User-agent: *
+Disallow: /admin/
+Allow: /admin/public/
+Disallow: /*.php$
+Crawl-delay: 2
+
+Sitemap: https://example.com/sitemap.xml
+";
+
+    #[test]
+    fn the_named_group_is_picked_before_the_wildcard() {
+        let robots = RobotsInfo::parse(ROBOTS);
+        let googlebot = robots.group_for("Mozilla/5.0 (compatible; Googlebot/2.1)").unwrap();
+        assert!(!googlebot.is_allowed("/nogoogle/page"));
+        assert!(googlebot.is_allowed("/admin/"));
+
+        let other = robots.group_for("rust-web-osint-scraper/0.1").unwrap();
+        assert!(other.is_allowed("/nogoogle/page"));
+        assert_eq!(other.crawl_delay(), Some(Duration::from_secs(2)));
+        assert_eq!(robots.sitemaps, ["https://example.com/sitemap.xml"]);
+    }
+
+    #[test]
+    fn the_longest_rule_wins_and_patterns_are_anchored() {
+        let robots = RobotsInfo::parse(ROBOTS);
+        let group = robots.group_for("curl/8.0").unwrap();
+        assert!(!group.is_allowed("/admin/users"));
+        assert!(group.is_allowed("/admin/public/logo.png"));
+        assert!(!group.is_allowed("/index.php"));
+        assert!(group.is_allowed("/index.php?page=1"));
+        assert!(group.is_allowed("/about"));
+    }
+
+    #[test]
+    fn wildcard_patterns_become_probe_candidates() {
+        assert_eq!(expand_pattern("/admin/*.php$"), ["/admin/", "/admin/.php"]);
+        assert_eq!(expand_pattern("/private*/"), ["/private", "/private/"]);
+        assert_eq!(expand_pattern("secret//file"), ["/secret/file"]);
+        assert!(expand_pattern("*").is_empty());
+        assert_eq!(expand_pattern("/*.php$"), ["/.php"]);
+    }
+}
@@ -1,22 +1,33 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs::{self, create_dir_all, File, read_dir};
 use std::io::{Write, BufWriter};
-use std::path::{Path, PathBuf};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use scraper::{Html, Selector};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::secrets::{self, SecretFinding};
+use crate::technologies::{self, Evidence, Technology};
+use crate::{headers, listing, resources};
 
-pub fn get_intel_dir_path() -> PathBuf {
-    let found_html_path = Path::new("found_html");
+//set by --quiet, silences the Debug: output of the scanner
+static QUIET: AtomicBool = AtomicBool::new(false);
 
-    found_html_path
-        .parent()
-        .unwrap_or_else(|| Path::new("."))
-        .join("intel")
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
 }
 
-#[derive(Debug, Default)]
+macro_rules! debug_log {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            println!($($arg)*);
+        }
+    };
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct ScannerInfos {
-    pub webserver: Vec<String>,
+    pub webserver: HashSet<String>,
     pub technologies: HashSet<String>,
     pub emails: HashSet<String>,
     pub scripts: HashSet<String>,
@@ -24,10 +35,128 @@ pub struct ScannerInfos {
     pub meta_tags: Vec<String>,
     pub links: HashSet<String>,
     pub api_endpoints: HashSet<String>,
+    pub secrets: HashSet<String>,
+    //"<url> -> <status> -> <target>" for every probe answered with a redirect
+    pub redirects: HashSet<String>,
+    //"<path> (<size>, <date>)" for every entry of a directory listing page
+    pub directory_listings: HashSet<String>,
+    //version, categories and confidence of each entry in `technologies`
+    pub technology_details: HashMap<String, Technology>,
+    //rule and locations of each entry in `secrets`
+    pub secret_details: HashMap<String, SecretFinding>,
+    //how often each value was seen, per category
+    pub occurrences: HashMap<String, HashMap<String, usize>>,
+}
+
+impl ScannerInfos {
+    pub fn count(&mut self, category: &'static str, value: &str) {
+        *self
+            .occurrences
+            .entry(category.to_string())
+            .or_default()
+            .entry(value.to_string())
+            .or_insert(0) += 1;
+    }
+
+    pub fn add_technology(&mut self, technology: Technology) {
+        let label = technology.label();
+        self.count("technologies", &label);
+        self.technologies.insert(label.clone());
+        match self.technology_details.get_mut(&label) {
+            Some(known) => known.confidence = known.confidence.max(technology.confidence),
+            None => {
+                self.technology_details.insert(label, technology);
+            }
+        }
+    }
+
+    pub fn occurrences_of(&self, category: &str, value: &str) -> usize {
+        self.occurrences
+            .get(category)
+            .and_then(|values| values.get(value))
+            .copied()
+            .unwrap_or(1)
+    }
+
+    //every distinct finding grouped by category, in the order the intel files are written
+    pub fn findings(&self) -> Vec<(&'static str, BTreeSet<&String>)> {
+        vec![
+            ("technologies", self.technologies.iter().collect()),
+            ("emails", self.emails.iter().collect()),
+            ("scripts", self.scripts.iter().collect()),
+            ("comments", self.comments.iter().collect()),
+            ("meta_tags", self.meta_tags.iter().collect()),
+            ("links", self.links.iter().collect()),
+            ("api_endpoints", self.api_endpoints.iter().collect()),
+            ("secrets", self.secrets.iter().collect()),
+            ("webserver", self.webserver.iter().collect()),
+            ("redirects", self.redirects.iter().collect()),
+            ("directory_listings", self.directory_listings.iter().collect()),
+        ]
+    }
+
+    pub fn merge(&mut self, other: ScannerInfos) {
+        self.technologies.extend(other.technologies);
+        self.emails.extend(other.emails);
+        self.scripts.extend(other.scripts);
+        self.comments.extend(other.comments);
+        self.meta_tags.extend(other.meta_tags);
+        self.links.extend(other.links);
+        self.api_endpoints.extend(other.api_endpoints);
+        self.secrets.extend(other.secrets);
+        self.webserver.extend(other.webserver);
+        self.redirects.extend(other.redirects);
+        self.directory_listings.extend(other.directory_listings);
+        for (label, technology) in other.technology_details {
+            let known = self.technology_details.entry(label).or_insert_with(|| technology.clone());
+            known.confidence = known.confidence.max(technology.confidence);
+        }
+        for (label, finding) in other.secret_details {
+            match self.secret_details.get_mut(&label) {
+                Some(known) => known.locations.extend(finding.locations),
+                None => {
+                    self.secret_details.insert(label, finding);
+                }
+            }
+        }
+        for (category, values) in other.occurrences {
+            let counts = self.occurrences.entry(category).or_default();
+            for (value, count) in values {
+                *counts.entry(value).or_insert(0) += count;
+            }
+        }
+    }
+}
+
+//Regex for Email addresses, shared with the resource scanners
+pub fn extract_emails(content: &str, info: &mut ScannerInfos) -> Result<(), regex::Error> {
+    let re_email = Regex::new(r"[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}")?;
+    for cap in re_email.captures_iter(content) {
+        if let Some(email) = cap.get(0) {
+            info.emails.insert(email.as_str().to_string());
+            info.count("emails", email.as_str());
+        }
+    }
+    Ok(())
+}
+
+pub fn extract_api_endpoints(content: &str, info: &mut ScannerInfos) -> Result<(), regex::Error> {
+    //stops at quotes and call syntax so paths inside minified scripts come out clean
+    let re_api = Regex::new(r#"/(api|gen_204|client_204|log|og/_/js|_/js|_/ss|graphql|rest)/[^?\s'"`<>$(){};,\\]*"#)?;
+    for cap in re_api.captures_iter(content) {
+        if let Some(api) = cap.get(0) {
+            let api_str = api.as_str();
+            if api_str.len() > 4 && !api_str.contains("://") {
+                info.api_endpoints.insert(api_str.to_string());
+                info.count("api_endpoints", api_str);
+            }
+        }
+    }
+    Ok(())
 }
 
 pub fn scan_for_information(filename: String) -> Result<ScannerInfos, Box<dyn std::error::Error>> {
-    println!("Debug: Scanning file: {}", filename);
+    debug_log!("Debug: Scanning file: {}", filename);
 
     let mut info = ScannerInfos::default();
     let content = fs::read_to_string(&filename)?;
@@ -37,6 +166,7 @@ pub fn scan_for_information(filename: String) -> Result<ScannerInfos, Box<dyn st
     for element in document.select(&script_selector) {
         if let Some(src) = element.value().attr("src") {
             info.scripts.insert(src.to_string());
+            info.count("scripts", src);
         }
     }
 
@@ -44,17 +174,19 @@ pub fn scan_for_information(filename: String) -> Result<ScannerInfos, Box<dyn st
     for element in document.select(&link_css_selector) {
         if let Some(href) = element.value().attr("href") {
             info.scripts.insert(href.to_string());
+            info.count("scripts", href);
         }
     }
 
     let link_href_selector = Selector::parse("a[href]").unwrap();
     for element in document.select(&link_href_selector) {
-        if let Some(href) = element.value().attr("href") {
-            if href.starts_with("http") {
-                info.links.insert(href.to_string());
-            }
-            //Logic for scanning Internal Links could be added here
+        if let Some(href) = element.value().attr("href")
+            && href.starts_with("http")
+        {
+            info.links.insert(href.to_string());
+            info.count("links", href);
         }
+        //Internal links are followed by the crawler (--crawl)
     }
 
     let meta_name_selector = Selector::parse("meta[name][content]").unwrap();
@@ -62,7 +194,9 @@ pub fn scan_for_information(filename: String) -> Result<ScannerInfos, Box<dyn st
         let name = element.value().attr("name").unwrap_or("");
         let content_val = element.value().attr("content").unwrap_or("");
         if !name.is_empty() && !content_val.is_empty() {
-            info.meta_tags.push(format!("{}: {}", name, content_val));
+            let meta_tag = format!("{}: {}", name, content_val);
+            info.count("meta_tags", &meta_tag);
+            info.meta_tags.push(meta_tag);
         }
     }
 
@@ -74,25 +208,12 @@ pub fn scan_for_information(filename: String) -> Result<ScannerInfos, Box<dyn st
         }
     }
 
-    let document_text_lower = document.root_element().text().collect::<String>().to_lowercase();
-    let tech_keywords = [
-        "google", "gws", "nginx", "apache", "react", "angular", "vue.js", "webpack", "jquery",
-        "adservice", "gstatic", "googlesyndication", "analytics", "gtag", "closure library",
-        "trustedtypes", "gapi", "material", "lit", "polymer", "bootstrap", "font awesome",
-    ];
-    for tech in &tech_keywords {
-        if document_text_lower.contains(&tech.to_lowercase()) {
-            info.technologies.insert(tech.to_string());
-        }
+    for technology in technologies::detect(&Evidence::from_document(&content, &document)) {
+        info.add_technology(technology);
     }
 
-    //Regex for Email addresses
-    let re_email = Regex::new(r"[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}")?;
-    for cap in re_email.captures_iter(&content) {
-        if let Some(email) = cap.get(0) {
-            info.emails.insert(email.as_str().to_string());
-        }
-    }
+    extract_emails(&content, &mut info)?;
+    secrets::scan_secrets(&content, &filename, &mut info);
 
     let re_comment = Regex::new(r"(?s)<!--(.*?)-->")?;
     for cap in re_comment.captures_iter(&content) {
@@ -100,24 +221,28 @@ pub fn scan_for_information(filename: String) -> Result<ScannerInfos, Box<dyn st
             let comment_text = comment_match.as_str().trim();
             if !comment_text.is_empty() {
                 info.comments.push(comment_text.to_string());
+                info.count("comments", comment_text);
             }
         }
     }
 
-    let re_api = Regex::new(r"/(api|gen_204|client_204|log|og/_/js|_/js|_/ss|graphql|rest)/[^?\s\'<>]*")?;
-    for cap in re_api.captures_iter(&content) {
-         if let Some(api) = cap.get(0) {
-             let api_str = api.as_str();
-             if api_str.len() > 4 && !api_str.contains("://") {
-            info.api_endpoints.insert(api_str.to_string());
-             }
-         }
+    extract_api_endpoints(&content, &mut info)?;
+
+    //autoindex pages of Apache, nginx, IIS and friends: every listed file is a finding
+    if listing::is_directory_listing(&content) {
+        let directory = listing::listed_directory(&content);
+        for entry in listing::parse_listing(&content) {
+            let value = entry.describe(directory.as_deref());
+            info.count("directory_listings", &value);
+            info.directory_listings.insert(value);
+        }
     }
 
     let data_src_selector = Selector::parse("[data-src]").unwrap();
     for element in document.select(&data_src_selector) {
         if let Some(data_src) = element.value().attr("data-src") {
             info.scripts.insert(data_src.to_string());
+            info.count("scripts", data_src);
         }
     }
 
@@ -125,12 +250,11 @@ pub fn scan_for_information(filename: String) -> Result<ScannerInfos, Box<dyn st
 }
 
 //writes all collected information to files in the intel directory
-fn write_summary_to_files(all_info: ScannerInfos) -> Result<(), Box<dyn std::error::Error>> {
-    let intel_dir_path = get_intel_dir_path();
+fn write_summary_to_files(all_info: ScannerInfos, intel_dir_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
     let intel_dir_str = intel_dir_path.to_string_lossy();
 
-    create_dir_all(&intel_dir_path)?;
-    println!("Debug: Ensuring intel directory exists at: {}", intel_dir_str);
+    create_dir_all(intel_dir_path)?;
+    debug_log!("Debug: Ensuring intel directory exists at: {}", intel_dir_str);
 
     fn write_items_to_file<S>(
         base_dir: &Path,
@@ -153,26 +277,48 @@ fn write_summary_to_files(all_info: ScannerInfos) -> Result<(), Box<dyn std::err
         Ok(())
     }
 
-    println!("Debug: Writing to files - Techs: {}, Emails: {}, Scripts: {}, Comments: {}, Meta: {}, Links: {}, APIs: {}",
+    debug_log!("Debug: Writing to files - Techs: {}, Emails: {}, Scripts: {}, Comments: {}, Meta: {}, Links: {}, APIs: {}, Secrets: {}",
              all_info.technologies.len(),
              all_info.emails.len(),
              all_info.scripts.len(),
              all_info.comments.len(),
              all_info.meta_tags.len(),
              all_info.links.len(),
-             all_info.api_endpoints.len()
+             all_info.api_endpoints.len(),
+             all_info.secrets.len()
     );
 
-    write_items_to_file(&intel_dir_path, "technologies.txt", &all_info.technologies)?;
-    write_items_to_file(&intel_dir_path, "emails.txt", &all_info.emails)?;
-    write_items_to_file(&intel_dir_path, "scripts.txt", &all_info.scripts)?;
-    write_items_to_file(&intel_dir_path, "comments.txt", &all_info.comments)?;
-    write_items_to_file(&intel_dir_path, "meta_tags.txt", &all_info.meta_tags)?;
-    write_items_to_file(&intel_dir_path, "links.txt", &all_info.links)?;
-    write_items_to_file(&intel_dir_path, "api_endpoints.txt", &all_info.api_endpoints)?;
+    let technology_lines: BTreeSet<String> = all_info
+        .technologies
+        .iter()
+        .map(|label| match all_info.technology_details.get(label) {
+            Some(technology) => technology.to_string(),
+            None => label.clone(),
+        })
+        .collect();
+    write_items_to_file(intel_dir_path, "technologies.txt", &technology_lines)?;
+    write_items_to_file(intel_dir_path, "emails.txt", &all_info.emails)?;
+    write_items_to_file(intel_dir_path, "scripts.txt", &all_info.scripts)?;
+    write_items_to_file(intel_dir_path, "comments.txt", &all_info.comments)?;
+    write_items_to_file(intel_dir_path, "meta_tags.txt", &all_info.meta_tags)?;
+    write_items_to_file(intel_dir_path, "links.txt", &all_info.links)?;
+    write_items_to_file(intel_dir_path, "api_endpoints.txt", &all_info.api_endpoints)?;
+    let secret_lines: BTreeSet<String> = all_info
+        .secrets
+        .iter()
+        .map(|label| match all_info.secret_details.get(label) {
+            Some(finding) => finding.to_string(),
+            None => label.clone(),
+        })
+        .collect();
+    write_items_to_file(intel_dir_path, "secrets.txt", &secret_lines)?;
+
+    if !all_info.directory_listings.is_empty() {
+        write_items_to_file(intel_dir_path, "directory_listings.txt", &all_info.directory_listings)?;
+    }
 
     if !all_info.webserver.is_empty() {
-        write_items_to_file(&intel_dir_path, "webserver.txt", &all_info.webserver)?;
+        write_items_to_file(intel_dir_path, "webserver.txt", &all_info.webserver)?;
     } else {
         let path = intel_dir_path.join("webserver.txt");
         File::create(&path)?;
@@ -182,10 +328,28 @@ fn write_summary_to_files(all_info: ScannerInfos) -> Result<(), Box<dyn std::err
     Ok(())
 }
 
-//main function for scanning all html files in the found_html directory
-pub fn scan_all_html_files() -> Result<(), Box<dyn std::error::Error>> {
-    let found_html_path_str = "found_html";
-    let found_html_path = Path::new(found_html_path_str);
+//every file in `dir` and its subdirectories, e.g. sources unpacked from source maps
+fn files_below(dir: &Path) -> Result<Vec<std::path::PathBuf>, std::io::Error> {
+    let mut files = Vec::new();
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(files_below(&path)?);
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+//main function for scanning all html files in the found_html directory of a run,
+//saved non-HTML resources are scanned alongside with the content-aware scanners
+pub fn scan_all_html_files(
+    found_html_path: &Path,
+    resource_dir_path: &Path,
+    intel_dir_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let found_html_path_str = found_html_path.display();
     if !found_html_path.exists() {
         eprintln!("Directory '{}' not found. Nothing to scan.", found_html_path_str);
         return Ok(());
@@ -196,7 +360,7 @@ pub fn scan_all_html_files() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     let entries = read_dir(found_html_path)?;
-    println!("Debug: Looking for HTML files in directory: {}", found_html_path_str);
+    debug_log!("Debug: Looking for HTML files in directory: {}", found_html_path_str);
 
     let mut all_collected_info = ScannerInfos::default();
     let mut files_scanned = 0;
@@ -206,7 +370,18 @@ pub fn scan_all_html_files() -> Result<(), Box<dyn std::error::Error>> {
         let entry = entry?;
         let path = entry.path();
 
-        if path.is_file() {
+        if path.is_file() && path.file_name().is_some_and(|name| name == headers::HEADER_LOG_FILE) {
+            match headers::scan_header_log(&path) {
+                Ok(header_info) => {
+                    debug_log!("Debug: Found {} webserver fingerprints in response headers", header_info.webserver.len());
+                    all_collected_info.merge(header_info);
+                }
+                Err(e) => {
+                    eprintln!("Error scanning {}: {}", path.display(), e);
+                    files_with_errors += 1;
+                }
+            }
+        } else if path.is_file() {
             if let Some(extension) = path.extension() {
                 if extension == "html" || extension == "htm" || extension == "txt" {
                     let filename_str = path.to_string_lossy().to_string();
@@ -215,8 +390,8 @@ pub fn scan_all_html_files() -> Result<(), Box<dyn std::error::Error>> {
 
                     match scan_for_information(filename_str) {
                         Ok(file_info) => {
-                            println!("Debug: Successfully scanned file.");
-                            println!("Debug: Found {} techs, {} emails, {} scripts, {} comments, {} meta tags, {} links, {} APIs",
+                            debug_log!("Debug: Successfully scanned file.");
+                            debug_log!("Debug: Found {} techs, {} emails, {} scripts, {} comments, {} meta tags, {} links, {} APIs",
                                      file_info.technologies.len(),
                                      file_info.emails.len(),
                                      file_info.scripts.len(),
@@ -226,14 +401,7 @@ pub fn scan_all_html_files() -> Result<(), Box<dyn std::error::Error>> {
                                      file_info.api_endpoints.len()
                             );
 
-                            all_collected_info.technologies.extend(file_info.technologies);
-                            all_collected_info.emails.extend(file_info.emails);
-                            all_collected_info.scripts.extend(file_info.scripts);
-                            all_collected_info.comments.extend(file_info.comments);
-                            all_collected_info.meta_tags.extend(file_info.meta_tags);
-                            all_collected_info.links.extend(file_info.links);
-                            all_collected_info.api_endpoints.extend(file_info.api_endpoints);
-                            all_collected_info.webserver.extend(file_info.webserver);
+                            all_collected_info.merge(file_info);
                         }
                         Err(e) => {
                             eprintln!("Error scanning {}: {}", path.display(), e);
@@ -241,17 +409,32 @@ pub fn scan_all_html_files() -> Result<(), Box<dyn std::error::Error>> {
                         }
                     }
                 } else {
-                    println!("Debug: Skipping file with extension: {:?}", extension);
+                    debug_log!("Debug: Skipping file with extension: {:?}", extension);
                 }
             } else {
-                println!("Debug: Skipping file without extension: {:?}", path);
+                debug_log!("Debug: Skipping file without extension: {:?}", path);
             }
         } else {
-            println!("Debug: Skipping directory entry: {:?}", path);
+            debug_log!("Debug: Skipping directory entry: {:?}", path);
         }
     }
 
     println!("Finished scanning {} HTML files ({} errors).", files_scanned, files_with_errors);
+
+    if resource_dir_path.is_dir() {
+        let mut resources_scanned = 0;
+        for path in files_below(resource_dir_path)? {
+            match resources::scan_resource_file(&path) {
+                Ok(file_info) => {
+                    resources_scanned += 1;
+                    all_collected_info.merge(file_info);
+                }
+                Err(e) => eprintln!("Error scanning {}: {}", path.display(), e),
+            }
+        }
+        println!("Finished scanning {} saved resources.", resources_scanned);
+    }
+
     println!("Total unique items found across all files:");
     println!("  Technologies: {}", all_collected_info.technologies.len());
     println!("  Emails: {}", all_collected_info.emails.len());
@@ -260,10 +443,12 @@ pub fn scan_all_html_files() -> Result<(), Box<dyn std::error::Error>> {
     println!("  Meta Tags: {}", all_collected_info.meta_tags.len());
     println!("  Links: {}", all_collected_info.links.len());
     println!("  API Endpoints: {}", all_collected_info.api_endpoints.len());
+    println!("  Secrets: {}", all_collected_info.secrets.len());
+    println!("  Webserver: {}", all_collected_info.webserver.len());
+    println!("  Directory Listings: {}", all_collected_info.directory_listings.len());
 
-    let intel_dir_path = get_intel_dir_path();
     println!("Creating intelligence summary in '{}' directory...", intel_dir_path.display());
-    write_summary_to_files(all_collected_info)?; //make sure this is called after all files are scanned
+    write_summary_to_files(all_collected_info, intel_dir_path)?; //make sure this is called after all files are scanned
 
     println!("All HTML files scanned. Summary written to '{}/' directory.", intel_dir_path.display());
     Ok(())
@@ -0,0 +1,303 @@
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use chrono::Utc;
+use reqwest::cookie::Jar;
+use scraper::{ElementRef, Html, Selector};
+use url::Url;
+use crate::http::{self, HttpClient};
+use crate::report::ProbeRecord;
+
+//path fragments of links that end a session, not followed while logged in
+const LOGOUT_MARKERS: &[&str] = &["logout", "log-out", "log_out", "logoff", "signout", "sign-out", "sign_out"];
+
+//Loads a Netscape cookie file (curl -c, browser export add-ons) into `jar`:
+//domain, include-subdomains flag, path, secure flag, expiry, name, value; tab-separated.
+//Returns the number of cookies loaded, expired ones are skipped.
+pub fn load_cookie_file(path: &Path, jar: &Jar) -> Result<usize, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    let now = Utc::now().timestamp();
+    let mut loaded = 0;
+
+    for (index, line) in content.lines().enumerate() {
+        //curl marks HttpOnly cookies with a prefix that would otherwise read as a comment
+        let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [domain, include_subdomains, cookie_path, secure, expiry, name, value] = fields[..] else {
+            return Err(format!("{}:{}: expected 7 tab-separated fields", path.display(), index + 1).into());
+        };
+        let expiry: i64 = expiry.trim().parse().unwrap_or(0);
+        if expiry != 0 && expiry < now {
+            continue;
+        }
+
+        let host = domain.trim_start_matches('.');
+        let secure = secure.eq_ignore_ascii_case("TRUE");
+        let url = Url::parse(&format!("{}://{}{}", if secure { "https" } else { "http" }, host, cookie_path))?;
+        let mut cookie = format!("{}={}; Path={}", name, value, cookie_path);
+        //without a Domain attribute the cookie is only sent to this exact host
+        if include_subdomains.eq_ignore_ascii_case("TRUE") {
+            cookie.push_str(&format!("; Domain={}", host));
+        }
+        if secure {
+            cookie.push_str("; Secure");
+        }
+        jar.add_cookie_str(&cookie, &url);
+        loaded += 1;
+    }
+    Ok(loaded)
+}
+
+//"user=admin&password=secret" into form fields
+pub fn parse_form_data(data: &str) -> Vec<(String, String)> {
+    url::form_urlencoded::parse(data.trim().as_bytes())
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect()
+}
+
+pub fn is_logout_path(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    LOGOUT_MARKERS.iter().any(|marker| path.contains(marker))
+}
+
+//the fields a browser would submit for `form`: named inputs with their values, checked boxes,
+//selected options and textareas; buttons and file inputs are left out
+fn form_fields(form: &ElementRef) -> Vec<(String, String)> {
+    let field_selector = Selector::parse("input[name], select[name], textarea[name]").unwrap();
+    let option_selector = Selector::parse("option").unwrap();
+
+    let mut fields = Vec::new();
+    for field in form.select(&field_selector) {
+        let element = field.value();
+        let name = element.attr("name").unwrap_or_default().to_string();
+        let value = match element.name() {
+            "select" => {
+                let options: Vec<ElementRef> = field.select(&option_selector).collect();
+                let selected = options
+                    .iter()
+                    .find(|option| option.value().attr("selected").is_some())
+                    .or(options.first());
+                selected
+                    .map(|option| {
+                        option
+                            .value()
+                            .attr("value")
+                            .map(String::from)
+                            .unwrap_or_else(|| option.text().collect())
+                    })
+                    .unwrap_or_default()
+            }
+            "textarea" => field.text().collect(),
+            _ => {
+                let input_type = element.attr("type").unwrap_or("text").to_ascii_lowercase();
+                match input_type.as_str() {
+                    "submit" | "button" | "image" | "reset" | "file" => continue,
+                    "checkbox" | "radio" if element.attr("checked").is_none() => continue,
+                    "checkbox" | "radio" => element.attr("value").unwrap_or("on").to_string(),
+                    _ => element.attr("value").unwrap_or_default().to_string(),
+                }
+            }
+        };
+        fields.push((name, value));
+    }
+    fields
+}
+
+fn has_password_field(html: &str) -> bool {
+    let password_selector = Selector::parse("input[type=password i]").unwrap();
+    Html::parse_document(html).select(&password_selector).next().is_some()
+}
+
+//Logs in through the form on `login_url`. The page is fetched first so hidden fields (CSRF tokens)
+//and its cookies are kept; `credentials` fill in or override the form's fields. The session cookies
+//end up in the client's cookie jar and are sent with every later request.
+//Both requests are handed to `on_request` for the report.
+pub async fn form_login<F>(
+    client: &HttpClient,
+    login_url: &Url,
+    credentials: &[(String, String)],
+    mut on_request: F,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnMut(ProbeRecord),
+{
+    let started = Instant::now();
+    let page = match client.get(login_url.as_str()).await {
+        Ok(page) => page,
+        Err(e) => {
+            on_request(ProbeRecord::from_error(login_url.as_str(), &e, started));
+            return Err(e.into());
+        }
+    };
+    on_request(ProbeRecord::from_response(login_url.as_str(), &page, started));
+    let page_url = page.url().clone();
+    let html = page.text().await.unwrap_or_default();
+
+    //the form that asks for a password, otherwise the only form there is
+    let (action, mut fields) = {
+        let document = Html::parse_document(&html);
+        let form_selector = Selector::parse("form").unwrap();
+        let password_selector = Selector::parse("input[type=password i]").unwrap();
+        let forms: Vec<ElementRef> = document.select(&form_selector).collect();
+        let form = forms
+            .iter()
+            .find(|form| form.select(&password_selector).next().is_some())
+            .or(forms.first());
+        match form {
+            Some(form) => {
+                let action = form
+                    .value()
+                    .attr("action")
+                    .filter(|action| !action.trim().is_empty())
+                    .and_then(|action| page_url.join(action.trim()).ok())
+                    .unwrap_or_else(|| page_url.clone());
+                (action, form_fields(form))
+            }
+            None => (login_url.clone(), Vec::new()),
+        }
+    };
+
+    for (name, value) in credentials {
+        match fields.iter_mut().find(|(field, _)| field == name) {
+            Some(field) => field.1 = value.clone(),
+            None => fields.push((name.clone(), value.clone())),
+        }
+    }
+
+    let started = Instant::now();
+    let response = match client.post_form(action.as_str(), &fields).await {
+        Ok(response) => response,
+        Err(e) => {
+            on_request(ProbeRecord::from_error(action.as_str(), &e, started));
+            return Err(e.into());
+        }
+    };
+    on_request(ProbeRecord::from_response(action.as_str(), &response, started));
+    let status = response.status();
+    //with --no-follow-redirects the usual answer to a login is the redirect itself, its cookies are kept all the same
+    let landed_on = http::redirect_location(&response).unwrap_or_else(|| response.url().clone());
+    if !status.is_success() && !status.is_redirection() {
+        return Err(format!("Login at {} failed with status {}", action, status).into());
+    }
+    let body = response.text().await.unwrap_or_default();
+
+    println!(
+        "Logged in at {} (status {}, landed on {}), {} cookies in the session.",
+        action,
+        status,
+        landed_on,
+        client.cookie_count(&landed_on)
+    );
+    if has_password_field(&body) {
+        println!("Warning: the page after the login still asks for a password, the credentials may have been rejected.");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::cookie::CookieStore;
+
+    fn cookie_file(name: &str, content: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("cookies-test-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn cookies_for(jar: &Jar, url: &str) -> String {
+        jar.cookies(&Url::parse(url).unwrap())
+            .map(|value| value.to_str().unwrap().to_string())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn netscape_cookie_files_are_loaded() {
+        let path = cookie_file("netscape", "\
+# Netscape HTTP Cookie File
+#HttpOnly_example.com\tFALSE\t/\tFALSE\t0\tsession\tabc123
+.example.com\tTRUE\t/\tFALSE\t0\ttheme\tdark
+example.com\tFALSE\t/admin\tTRUE\t0\tadmin_token\tsecret
+example.com\tFALSE\t/\tFALSE\t1000000000\told\texpired
+");
+        let jar = Jar::default();
+        let loaded = load_cookie_file(&path, &jar).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, 3);
+        assert_eq!(cookies_for(&jar, "http://example.com/"), "session=abc123; theme=dark");
+        //only the include-subdomains cookie goes to other hosts of the domain
+        assert_eq!(cookies_for(&jar, "http://www.example.com/"), "theme=dark");
+        //secure cookies only go out over https, and only below their path
+        assert!(!cookies_for(&jar, "http://example.com/admin/").contains("admin_token"));
+        assert!(!cookies_for(&jar, "https://example.com/").contains("admin_token"));
+        assert!(cookies_for(&jar, "https://example.com/admin/users").contains("admin_token=secret"));
+    }
+
+    #[test]
+    fn name_value_lines_are_rejected_by_the_cookie_file_loader() {
+        let path = cookie_file("name-value", "session=abc123\n");
+        let error = load_cookie_file(&path, &Jar::default()).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(error.to_string().ends_with(":1: expected 7 tab-separated fields"));
+    }
+
+    #[test]
+    fn login_data_is_parsed_as_name_value_pairs() {
+        assert_eq!(
+            parse_form_data(" user=admin&password=p%40ss+word&remember \n"),
+            [
+                ("user".to_string(), "admin".to_string()),
+                ("password".to_string(), "p@ss word".to_string()),
+                ("remember".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn form_fields_are_the_ones_a_browser_submits() {
+        let html = Html::parse_document(r#"<form method="post">
+            <input type="hidden" name="csrf" value="t0ken">
+            <input name="user">
+            <input type="password" name="password">
+            <input type="checkbox" name="remember" checked>
+            <input type="checkbox" name="newsletter" value="yes">
+            <input type="radio" name="mode" value="basic">
+            <input type="radio" name="mode" value="expert" checked>
+            <select name="lang"><option value="en">English</option><option value="de" selected>Deutsch</option></select>
+            <select name="region"><option>North</option><option>South</option></select>
+            <textarea name="note">hello</textarea>
+            <input type="file" name="avatar">
+            <input type="submit" name="go" value="Sign in">
+            <button name="cancel">Cancel</button>
+        </form>"#);
+        let form = html.select(&Selector::parse("form").unwrap()).next().unwrap();
+        let fields = form_fields(&form);
+        let fields: Vec<(&str, &str)> = fields.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+        assert_eq!(
+            fields,
+            [
+                ("csrf", "t0ken"),
+                ("user", ""),
+                ("password", ""),
+                ("remember", "on"),
+                ("mode", "expert"),
+                ("lang", "de"),
+                ("region", "North"),
+                ("note", "hello"),
+            ]
+        );
+    }
+
+    #[test]
+    fn logout_paths_are_recognised() {
+        assert!(is_logout_path("/logout"));
+        assert!(is_logout_path("/account/Sign-Out?next=/"));
+        assert!(is_logout_path("/user/log_out.php"));
+        assert!(!is_logout_path("/login"));
+        assert!(!is_logout_path("/blog/logs"));
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::{HashSet, VecDeque};
+use std::fs::{File, create_dir_all};
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+use std::time::Instant;
+use flate2::read::GzDecoder;
+use regex::Regex;
+use url::Url;
+use crate::http::{self, ErrorClass, HttpClient};
+use crate::report::ProbeRecord;
+use crate::state;
+
+//the limit of the sitemap protocol, for the download as well as for a decompressed .xml.gz,
+//so a gzip bomb cannot exhaust memory
+const MAX_SITEMAP_SIZE: usize = 50 * 1024 * 1024;
+
+//locations probed for sitemaps in addition to the Sitemap: lines of robots.txt
+const WELL_KNOWN_SITEMAPS: &[&str] = &[
+    "sitemap.xml",
+    "sitemap_index.xml",
+    "sitemap-index.xml",
+    "sitemap.xml.gz",
+    "wp-sitemap.xml",
+    "sitemap.txt",
+];
+
+#[derive(Debug, Clone)]
+pub struct SitemapEntry {
+    pub url: String,
+    pub lastmod: Option<String>,
+    pub source: String,
+}
+
+enum SitemapDocument {
+    UrlSet(Vec<(String, Option<String>)>),
+    Index(Vec<String>),
+}
+
+fn decode_xml_text(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|t| t.strip_suffix("]]>"))
+        .unwrap_or(text);
+    text.trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+//gzip is detected by its magic bytes, servers rarely send a useful content-type for .xml.gz
+fn decompress_if_gzip(bytes: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut decoded = Vec::new();
+        GzDecoder::new(bytes)
+            .take(MAX_SITEMAP_SIZE as u64 + 1)
+            .read_to_end(&mut decoded)?;
+        if decoded.len() > MAX_SITEMAP_SIZE {
+            return Err(format!("decompresses to more than {} bytes", MAX_SITEMAP_SIZE).into());
+        }
+        Ok(String::from_utf8_lossy(&decoded).into_owned())
+    } else {
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+fn parse_sitemap(bytes: &[u8]) -> Result<SitemapDocument, Box<dyn std::error::Error>> {
+    let content = decompress_if_gzip(bytes)?;
+
+    //plain text sitemaps list one URL per line
+    if !content.trim_start().starts_with('<') {
+        let urls = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("http://") || line.starts_with("https://"))
+            .map(|line| (line.to_string(), None))
+            .collect();
+        return Ok(SitemapDocument::UrlSet(urls));
+    }
+
+    let re_entry = Regex::new(r"(?s)<(?:\w+:)?(url|sitemap)\b[^>]*>(.*?)</(?:\w+:)?(?:url|sitemap)>")?;
+    let re_loc = Regex::new(r"(?s)<(?:\w+:)?loc>(.*?)</(?:\w+:)?loc>")?;
+    let re_lastmod = Regex::new(r"(?s)<(?:\w+:)?lastmod>(.*?)</(?:\w+:)?lastmod>")?;
+    let is_index = Regex::new(r"<(?:\w+:)?sitemapindex\b")?.is_match(&content);
+
+    let mut urls = Vec::new();
+    let mut children = Vec::new();
+    for cap in re_entry.captures_iter(&content) {
+        let body = &cap[2];
+        let Some(loc) = re_loc.captures(body).map(|c| decode_xml_text(&c[1])) else {
+            continue;
+        };
+        if is_index && &cap[1] == "sitemap" {
+            children.push(loc);
+        } else {
+            let lastmod = re_lastmod.captures(body).map(|c| decode_xml_text(&c[1]));
+            urls.push((loc, lastmod));
+        }
+    }
+
+    if is_index {
+        Ok(SitemapDocument::Index(children))
+    } else {
+        Ok(SitemapDocument::UrlSet(urls))
+    }
+}
+
+async fn fetch_bytes(client: &HttpClient, url: &str) -> (ProbeRecord, Option<Vec<u8>>) {
+    let started = Instant::now();
+    let resp = match client.get(url).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            println!("Request failed for {} ({}): {}", url, ErrorClass::classify(&e), e);
+            return (ProbeRecord::from_error(url, &e, started), None);
+        }
+    };
+    let mut record = ProbeRecord::from_response(url, &resp, started);
+    if !resp.status().is_success() {
+        return (record, None);
+    }
+    match http::read_body_limited(resp, MAX_SITEMAP_SIZE).await {
+        Ok((bytes, false)) => {
+            record.size = Some(bytes.len() as u64);
+            (record, Some(bytes))
+        }
+        Ok((_, true)) => {
+            eprintln!("Skipping sitemap {}: larger than {} bytes", url, MAX_SITEMAP_SIZE);
+            (record, None)
+        }
+        Err(e) => {
+            eprintln!("Failed to read sitemap {}: {}", url, e);
+            record.set_error(&e);
+            (record, None)
+        }
+    }
+}
+
+//Collects all URLs from the robots.txt sitemaps and the well-known locations,
+//following sitemap indexes until `max_sitemaps` documents have been fetched.
+//Every request is handed to `on_request` for the report.
+pub async fn discover_sitemaps<F>(
+    client: &HttpClient,
+    base_url: &str,
+    robots_sitemaps: &[String],
+    max_sitemaps: usize,
+    mut on_request: F,
+) -> Vec<SitemapEntry>
+where
+    F: FnMut(ProbeRecord),
+{
+    let mut queue: VecDeque<String> = robots_sitemaps.iter().cloned().collect();
+    queue.extend(WELL_KNOWN_SITEMAPS.iter().map(|path| format!("{}{}", base_url, path)));
+    let origin = Url::parse(base_url).ok().map(|url| url.origin());
+
+    let mut seen = HashSet::new();
+    let mut seen_urls = HashSet::new();
+    let mut entries = Vec::new();
+    let mut fetched = 0;
+
+    while let Some(sitemap_url) = queue.pop_front() {
+        if fetched >= max_sitemaps {
+            break;
+        }
+        if !seen.insert(sitemap_url.clone()) {
+            continue;
+        }
+
+        fetched += 1;
+        let (record, bytes) = tokio::select! {
+            result = fetch_bytes(client, &sitemap_url) => result,
+            _ = state::wait_for_interrupt() => break,
+        };
+        on_request(record);
+        let Some(bytes) = bytes else {
+            continue;
+        };
+
+        match parse_sitemap(&bytes) {
+            Ok(SitemapDocument::Index(children)) => {
+                println!("Sitemap index {} lists {} sitemaps.", sitemap_url, children.len());
+                //an index can point anywhere, only sitemaps of the target itself are followed
+                let (own, foreign): (Vec<String>, Vec<String>) = children.into_iter().partition(|child| {
+                    Url::parse(child).is_ok_and(|child| Some(child.origin()) == origin)
+                });
+                if !foreign.is_empty() {
+                    println!("Skipping {} sitemaps of {} on other hosts.", foreign.len(), sitemap_url);
+                }
+                queue.extend(own);
+            }
+            Ok(SitemapDocument::UrlSet(urls)) => {
+                println!("Sitemap {} lists {} URLs.", sitemap_url, urls.len());
+                for (url, lastmod) in urls {
+                    if seen_urls.insert(url.clone()) {
+                        entries.push(SitemapEntry {
+                            url,
+                            lastmod,
+                            source: sitemap_url.clone(),
+                        });
+                    }
+                }
+            }
+            Err(e) => eprintln!("Failed to parse sitemap {}: {}", sitemap_url, e),
+        }
+    }
+
+    entries
+}
+
+//path (with query) of every sitemap URL on the target origin, ready to be queued into the scan
+pub fn same_origin_paths(entries: &[SitemapEntry], base_url: &str) -> Vec<String> {
+    let Ok(base) = Url::parse(base_url) else {
+        return vec![];
+    };
+
+    entries
+        .iter()
+        .filter_map(|entry| Url::parse(&entry.url).ok())
+        .filter(|url| url.origin() == base.origin())
+        .map(|url| match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        })
+        .collect()
+}
+
+//writes every discovered URL with its lastmod and source sitemap to <intel>/sitemap_urls.txt
+pub fn write_sitemap_intel(entries: &[SitemapEntry], intel_dir_path: &Path) -> Result<(), std::io::Error> {
+    create_dir_all(intel_dir_path)?;
+
+    let path = intel_dir_path.join("sitemap_urls.txt");
+    let mut writer = BufWriter::new(File::create(&path)?);
+    for entry in entries {
+        writeln!(
+            writer,
+            "{}\t{}\t{}",
+            entry.url,
+            entry.lastmod.as_deref().unwrap_or("-"),
+            entry.source
+        )?;
+    }
+    writer.flush()?;
+    println!("Intel summary written to {} ({} items)", path.display(), entries.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+
+    fn gzip(content: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn gzip_sitemaps_are_decompressed_up_to_the_limit() {
+        let sitemap = b"<urlset><url><loc>https://example.com/a</loc><lastmod>2024-01-31</lastmod></url></urlset>";
+        let Ok(SitemapDocument::UrlSet(urls)) = parse_sitemap(&gzip(sitemap)) else {
+            panic!("expected a url set");
+        };
+        assert_eq!(urls, [("https://example.com/a".to_string(), Some("2024-01-31".to_string()))]);
+
+        let bomb = gzip(&vec![b' '; MAX_SITEMAP_SIZE + 1]);
+        assert!(decompress_if_gzip(&bomb).is_err());
+    }
+
+    #[test]
+    fn indexes_list_their_child_sitemaps() {
+        let index = b"<sitemapindex><sitemap><loc>https://example.com/s1.xml?a=1&amp;b=2</loc></sitemap></sitemapindex>";
+        let Ok(SitemapDocument::Index(children)) = parse_sitemap(index) else {
+            panic!("expected an index");
+        };
+        assert_eq!(children, ["https://example.com/s1.xml?a=1&b=2"]);
+    }
+}
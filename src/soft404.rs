@@ -0,0 +1,264 @@
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::LazyLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use clap::ValueEnum;
+use regex::Regex;
+use crate::http::{self, HttpClient};
+use crate::report::ProbeRecord;
+use crate::state;
+
+//what happens to responses that look like the calibrated "not found" page
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Soft404Mode {
+    /// Do not save or scan matching responses (default)
+    Suppress,
+    /// Save matching responses but mark them as soft-404s
+    Flag,
+    /// Skip calibration and keep every response
+    Off,
+}
+
+static TITLE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+
+//characters that continue a path segment, "/js" followed by one of them is a different path
+fn continues_segment(c: char) -> bool {
+    c.is_alphanumeric() || "-_.~%+".contains(c)
+}
+
+//`text` without the occurrences of `path` (given without its leading slash) that stand for the
+//requested path: right after a "/" or its encoded "%2F", and not followed by more of the segment.
+//"/js" is taken out of "/js/app.js" and "/js not found", but not out of "/jsmith" or "json".
+fn remove_path(text: &str, path: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (start, _) in text.match_indices(path) {
+        let end = start + path.len();
+        if start < copied {
+            continue;
+        }
+        let before = &text.as_bytes()[..start];
+        let after_slash = before.ends_with(b"/") || before.len() >= 3 && before[before.len() - 3..].eq_ignore_ascii_case(b"%2f");
+        if after_slash && !text[end..].chars().next().is_some_and(continues_segment) {
+            result.push_str(&text[copied..start]);
+            copied = end;
+        }
+    }
+    result.push_str(&text[copied..]);
+    result
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseFingerprint {
+    pub status: u16,
+    pub length: usize,
+    pub words: usize,
+    pub title: Option<String>,
+    pub hash: u64,
+    //where the response redirected to, with the requested path taken out
+    pub redirect: Option<String>,
+}
+
+impl ResponseFingerprint {
+    //`requested_path` is removed from the body and the redirect target first,
+    //error pages often echo it back and wildcard redirects carry it along
+    pub fn new(status: u16, body: &str, requested_path: &str, redirect: Option<&str>) -> Self {
+        let path = requested_path.trim_start_matches('/');
+        let without_path = |text: &str| if path.is_empty() { text.to_string() } else { remove_path(text, path) };
+        let body = without_path(body);
+
+        let title = TITLE.captures(&body).map(|c| c[1].trim().to_string());
+
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+
+        ResponseFingerprint {
+            status,
+            length: body.len(),
+            words: body.split_whitespace().count(),
+            title,
+            hash: hasher.finish(),
+            redirect: redirect.map(without_path),
+        }
+    }
+
+    //1.0 for identical bodies; otherwise the smaller of the length and word count ratios,
+    //and 0.0 when status, title or redirect target differ
+    pub fn similarity(&self, other: &ResponseFingerprint) -> f64 {
+        if self.status != other.status || self.title != other.title || self.redirect != other.redirect {
+            return 0.0;
+        }
+        if self.hash == other.hash {
+            return 1.0;
+        }
+
+        let ratio = |a: usize, b: usize| {
+            if a.max(b) == 0 {
+                1.0
+            } else {
+                a.min(b) as f64 / a.max(b) as f64
+            }
+        };
+        ratio(self.length, other.length).min(ratio(self.words, other.words))
+    }
+}
+
+pub struct Soft404Detector {
+    pub baselines: Vec<ResponseFingerprint>,
+    pub threshold: f64,
+}
+
+fn random_token() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(nanos);
+    format!("{:016x}", hasher.finish())
+}
+
+impl Soft404Detector {
+    //requests a few paths that cannot exist and remembers what the server answers;
+    //every request is handed to `on_request` for the report
+    pub async fn calibrate<F>(client: &HttpClient, base_url: &str, threshold: f64, mut on_request: F) -> Self
+    where
+        F: FnMut(ProbeRecord),
+    {
+        let calibration_paths = [
+            format!("/{}", random_token()),
+            format!("/{}/", random_token()),
+            format!("/{}.php", random_token()),
+            format!("/.{}", random_token()),
+        ];
+
+        let mut baselines: Vec<ResponseFingerprint> = Vec::new();
+        for path in &calibration_paths {
+            let url = format!("{}{}", base_url.trim_end_matches('/'), path);
+            let started = Instant::now();
+            //Ctrl-C ends the calibration with the baselines found so far
+            let resp = tokio::select! {
+                resp = client.get(&url) => resp,
+                _ = state::wait_for_interrupt() => break,
+            };
+            let resp = match resp {
+                Ok(resp) => resp,
+                Err(e) => {
+                    on_request(ProbeRecord::from_error(&url, &e, started));
+                    continue;
+                }
+            };
+            //the calibration answers are the "not found" page by definition
+            let mut record = ProbeRecord::from_response(&url, &resp, started);
+            record.soft_404 = true;
+            let status = resp.status().as_u16();
+            let redirect = http::redirect_target(&resp);
+            let body = match resp.text().await {
+                Ok(body) => body,
+                Err(e) => {
+                    record.set_error(&e);
+                    on_request(record);
+                    continue;
+                }
+            };
+            record.size = Some(body.len() as u64);
+            on_request(record);
+
+            let fingerprint = ResponseFingerprint::new(status, &body, path, redirect.as_deref());
+            match &redirect {
+                Some(target) => println!(
+                    "Calibration {}: status {}, {} bytes, {} words, redirected to {}",
+                    path, fingerprint.status, fingerprint.length, fingerprint.words, target
+                ),
+                None => println!(
+                    "Calibration {}: status {}, {} bytes, {} words",
+                    path, fingerprint.status, fingerprint.length, fingerprint.words
+                ),
+            }
+            if !baselines.iter().any(|b| b.similarity(&fingerprint) >= threshold) {
+                baselines.push(fingerprint);
+            }
+        }
+
+        if baselines.iter().any(|b| (200..300).contains(&b.status)) {
+            println!("Warning: the target answers non-existent paths with a success status, soft-404 detection is active.");
+        }
+        if baselines.iter().any(|b| b.redirect.is_some()) {
+            println!("Warning: the target redirects non-existent paths, redirects to the same place are treated as soft-404s.");
+        }
+
+        Soft404Detector {
+            baselines,
+            threshold,
+        }
+    }
+
+    pub fn is_soft_404(&self, fingerprint: &ResponseFingerprint) -> bool {
+        self.baselines
+            .iter()
+            .any(|baseline| baseline.similarity(fingerprint) >= self.threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remove_path_only_takes_out_whole_segments() {
+        assert_eq!(remove_path("/js/app.js and /js not found", "js"), "//app.js and / not found");
+        assert_eq!(remove_path("/jsmith json", "js"), "/jsmith json");
+        assert_eq!(remove_path("?next=%2Fadmin", "admin"), "?next=%2F");
+        assert_eq!(remove_path("?next=%2fadmin.php", "admin"), "?next=%2fadmin.php");
+    }
+
+    #[test]
+    fn echoed_path_does_not_change_the_fingerprint() {
+        let page = |path: &str| format!("<html><title>Not Found</title><p>The page /{} does not exist.</p></html>", path);
+        let first = ResponseFingerprint::new(200, &page("3f9a1c"), "/3f9a1c", None);
+        let second = ResponseFingerprint::new(200, &page("backup-2024-old.zip"), "/backup-2024-old.zip", None);
+        assert_eq!(first.hash, second.hash);
+        assert_eq!(first.similarity(&second), 1.0);
+    }
+
+    #[test]
+    fn redirects_are_compared_without_the_requested_path() {
+        let first = ResponseFingerprint::new(302, "", "/a1b2", Some("/login?next=/a1b2"));
+        let second = ResponseFingerprint::new(302, "", "/admin", Some("/login?next=/admin"));
+        let elsewhere = ResponseFingerprint::new(302, "", "/admin", Some("/admin/"));
+        assert_eq!(first.similarity(&second), 1.0);
+        assert_eq!(first.similarity(&elsewhere), 0.0);
+    }
+
+    #[test]
+    fn similarity_is_the_smaller_of_the_length_and_word_ratios() {
+        let short = ResponseFingerprint::new(200, "one two three four", "/x", None);
+        let long = ResponseFingerprint::new(200, "one two three four five six seven eight", "/y", None);
+        let words = 4.0 / 8.0;
+        let length = short.length as f64 / long.length as f64;
+        assert_eq!(short.similarity(&long), length.min(words));
+        assert_eq!(long.similarity(&short), short.similarity(&long));
+    }
+
+    #[test]
+    fn different_status_or_title_is_never_similar() {
+        let page = "<title>Home</title>same body";
+        let ok = ResponseFingerprint::new(200, page, "/a", None);
+        assert_eq!(ok.similarity(&ResponseFingerprint::new(403, page, "/a", None)), 0.0);
+        assert_eq!(ok.similarity(&ResponseFingerprint::new(200, "<title>Login</title>same body", "/a", None)), 0.0);
+    }
+
+    #[test]
+    fn detector_matches_against_any_baseline() {
+        let detector = Soft404Detector {
+            baselines: vec![
+                ResponseFingerprint::new(200, "<title>Oops</title>nothing here", "/x", None),
+                ResponseFingerprint::new(302, "", "/y", Some("/")),
+            ],
+            threshold: 0.9,
+        };
+        assert!(detector.is_soft_404(&ResponseFingerprint::new(200, "<title>Oops</title>nothing here", "/admin", None)));
+        assert!(detector.is_soft_404(&ResponseFingerprint::new(302, "", "/admin", Some("/"))));
+        assert!(!detector.is_soft_404(&ResponseFingerprint::new(200, "<title>Admin</title>dashboard", "/admin", None)));
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use url::Url;
+use crate::output::target_dir_name;
+
+//written once the path list is known, lets --resume rebuild the same scan
+pub const STATE_FILE: &str = "scan_state.json";
+//one line per probed path, appended and flushed as the scan goes
+pub const PROGRESS_FILE: &str = "progress.jsonl";
+
+//set by the first Ctrl-C, the running phase stops and the run winds down normally
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static INTERRUPT: Notify = Notify::const_new();
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScanState {
+    pub target: String,
+    pub started_at: DateTime<Utc>,
+    //the fixed paths of the scan, in probe order: predefined, robots.txt, sitemaps;
+    //the wordlist paths follow them and are generated again from the wordlist on resume
+    pub paths: Vec<String>,
+    pub finished: bool,
+}
+
+//what became of a probed path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathProgress {
+    pub path: String,
+    pub status: Option<u16>,
+    //"html", "resource", "oversized", "status" or "error"
+    pub outcome: String,
+    //set when the path is a directory queued by --recursive, at this depth
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory_depth: Option<usize>,
+    //set when a followed redirect landed on another path, the page was saved under that one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_as: Option<String>,
+}
+
+impl ScanState {
+    pub fn load(run_dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(run_dir.join(STATE_FILE))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self, run_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        //written to a temporary file first so an interrupted write never leaves a broken state file
+        let temporary = run_dir.join(format!("{}.tmp", STATE_FILE));
+        let writer = BufWriter::new(File::create(&temporary)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        fs::rename(temporary, run_dir.join(STATE_FILE))?;
+        Ok(())
+    }
+}
+
+//the progress recorded so far, keyed by path; a line cut off by a kill is ignored
+pub fn load_progress(run_dir: &Path) -> Result<HashMap<String, PathProgress>, std::io::Error> {
+    let path = run_dir.join(PROGRESS_FILE);
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let mut progress = HashMap::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        if let Ok(entry) = serde_json::from_str::<PathProgress>(&line?) {
+            progress.insert(entry.path.clone(), entry);
+        }
+    }
+    Ok(progress)
+}
+
+pub struct ProgressLog {
+    writer: BufWriter<File>,
+}
+
+impl ProgressLog {
+    pub fn open(run_dir: &Path) -> Result<Self, std::io::Error> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(run_dir.join(PROGRESS_FILE))?;
+        Ok(ProgressLog {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    //flushed per entry, a killed run loses at most the path in flight
+    pub fn record(&mut self, entry: &PathProgress) -> Result<(), Box<dyn std::error::Error>> {
+        serde_json::to_writer(&mut self.writer, entry)?;
+        writeln!(self.writer)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+//the most recent unfinished run of `target` below `output_dir`
+pub fn find_resumable_run(output_dir: &Path, target: &Url) -> Option<PathBuf> {
+    let target_dir = output_dir.join(target_dir_name(target));
+    let mut runs: Vec<PathBuf> = fs::read_dir(target_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.join(STATE_FILE).is_file())
+        .collect();
+    //run directories are named by start time, so the name orders them
+    runs.sort();
+    runs.into_iter()
+        .rev()
+        .find(|run_dir| ScanState::load(run_dir).is_ok_and(|state| !state.finished))
+}
+
+//The first Ctrl-C asks the run to stop after the current probes, the second one exits at once.
+pub fn install_interrupt_handler() {
+    tokio::spawn(async {
+        loop {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            if INTERRUPTED.swap(true, Ordering::SeqCst) {
+                eprintln!("\nAborting.");
+                std::process::exit(130);
+            }
+            eprintln!("\nInterrupted, saving progress... (press Ctrl-C again to abort immediately)");
+            INTERRUPT.notify_waiters();
+        }
+    });
+}
+
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+//completes once Ctrl-C was pressed
+pub async fn wait_for_interrupt() {
+    let notified = INTERRUPT.notified();
+    tokio::pin!(notified);
+    //registered before the flag is checked, so an interrupt in between is not missed
+    notified.as_mut().enable();
+    if interrupted() {
+        return;
+    }
+    notified.await;
+}
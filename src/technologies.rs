@@ -0,0 +1,470 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::{LazyLock, OnceLock};
+use regex::Regex;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+
+//signatures shipped with the binary, --signatures adds to or overrides them by name
+const BUILTIN_SIGNATURES: &str = include_str!("../signatures/technologies.json");
+
+static SIGNATURES: OnceLock<Vec<Signature>> = OnceLock::new();
+//a \1..\9 reference in a version template
+static GROUP_REFERENCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\\(\d)").unwrap());
+
+//a string or a list of strings, both are accepted in signature files
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Patterns {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Patterns {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Patterns::One(pattern) => vec![pattern],
+            Patterns::Many(patterns) => patterns,
+        }
+    }
+}
+
+//one entry of a signature file, keyed by technology name
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RawSignature {
+    categories: Vec<String>,
+    scripts: Option<Patterns>,
+    html: Option<Patterns>,
+    meta: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    cookies: HashMap<String, String>,
+    js: HashMap<String, String>,
+}
+
+//A regex with optional "\;version:\1" and "\;confidence:50" suffixes. An empty
+//regex matches on presence alone (of a header, cookie or JS global).
+#[derive(Debug)]
+struct Pattern {
+    regex: Regex,
+    version: Option<String>,
+    confidence: u8,
+}
+
+impl Pattern {
+    fn parse(raw: &str) -> Result<Self, regex::Error> {
+        let mut parts = raw.split("\\;");
+        let expression = parts.next().unwrap_or("");
+        let mut version = None;
+        let mut confidence = 100;
+        for part in parts {
+            if let Some(template) = part.strip_prefix("version:") {
+                version = Some(template.to_string());
+            } else if let Some(value) = part.strip_prefix("confidence:") {
+                confidence = value.trim().parse().unwrap_or(100).min(100);
+            }
+        }
+        Ok(Pattern {
+            regex: Regex::new(&format!("(?i){}", expression))?,
+            version,
+            confidence,
+        })
+    }
+
+    //None when the pattern does not match, otherwise the version it extracted (if any)
+    fn matches(&self, text: &str) -> Option<Option<String>> {
+        let captures = self.regex.captures(text)?;
+        let version = self.version.as_ref().and_then(|template| {
+            let version = resolve_version(template, &captures);
+            (!version.is_empty()).then_some(version)
+        });
+        Some(version)
+    }
+}
+
+//substitutes \1..\9 and resolves "\1?yes:no" ternaries
+fn resolve_version(template: &str, captures: &regex::Captures) -> String {
+    let group = |index: &str| {
+        index
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| captures.get(i))
+            .map(|m| m.as_str())
+            .unwrap_or("")
+    };
+
+    if let Some((condition, branches)) = template.split_once('?')
+        && let Some(index) = condition.strip_prefix('\\')
+    {
+        let (yes, no) = branches.split_once(':').unwrap_or((branches, ""));
+        let chosen = if group(index).is_empty() { no } else { yes };
+        return resolve_version(chosen, captures);
+    }
+
+    GROUP_REFERENCE
+        .replace_all(template, |cap: &regex::Captures| group(&cap[1]).to_string())
+        .trim()
+        .to_string()
+}
+
+//JS globals cannot be evaluated without a browser, so inline scripts are searched for the
+//global being assigned or referenced; a version pattern is applied to an assigned string
+#[derive(Debug)]
+struct JsGlobal {
+    reference: Regex,
+    //name = "value" or name: "value"
+    assignment: Regex,
+    pattern: Pattern,
+}
+
+impl JsGlobal {
+    fn parse(global: &str, raw: &str) -> Result<Self, regex::Error> {
+        let name = regex::escape(global);
+        Ok(JsGlobal {
+            reference: Regex::new(&format!(r"(?:^|[^\w$.]){}(?:[^\w$]|$)", name))?,
+            assignment: Regex::new(&format!(r#"{}\s*[=:]\s*["']([^"']+)["']"#, name))?,
+            pattern: Pattern::parse(raw)?,
+        })
+    }
+
+    fn matches(&self, inline_scripts: &str) -> Option<Option<String>> {
+        if inline_scripts.is_empty() || !self.reference.is_match(inline_scripts) {
+            return None;
+        }
+        if self.pattern.version.is_none() {
+            return Some(None);
+        }
+        let version = self
+            .assignment
+            .captures_iter(inline_scripts)
+            .find_map(|cap| self.pattern.matches(&cap[1]).flatten());
+        Some(version)
+    }
+}
+
+#[derive(Debug)]
+struct Signature {
+    name: String,
+    categories: Vec<String>,
+    scripts: Vec<Pattern>,
+    html: Vec<Pattern>,
+    //keys are lowercased, meta names and header names are matched case-insensitively
+    meta: Vec<(String, Pattern)>,
+    headers: Vec<(String, Pattern)>,
+    cookies: Vec<(String, Pattern)>,
+    js: Vec<JsGlobal>,
+}
+
+fn parse_keyed(patterns: HashMap<String, String>, lowercase: bool) -> Result<Vec<(String, Pattern)>, regex::Error> {
+    patterns
+        .into_iter()
+        .map(|(key, raw)| {
+            let key = if lowercase { key.to_ascii_lowercase() } else { key };
+            Ok((key, Pattern::parse(&raw)?))
+        })
+        .collect()
+}
+
+fn parse_list(patterns: Option<Patterns>) -> Result<Vec<Pattern>, regex::Error> {
+    patterns
+        .map(Patterns::into_vec)
+        .unwrap_or_default()
+        .iter()
+        .map(|raw| Pattern::parse(raw))
+        .collect()
+}
+
+impl Signature {
+    fn compile(name: String, raw: RawSignature) -> Result<Self, regex::Error> {
+        Ok(Signature {
+            categories: raw.categories,
+            scripts: parse_list(raw.scripts)?,
+            html: parse_list(raw.html)?,
+            meta: parse_keyed(raw.meta, true)?,
+            headers: parse_keyed(raw.headers, true)?,
+            cookies: parse_keyed(raw.cookies, false)?,
+            js: raw
+                .js
+                .iter()
+                .map(|(global, raw)| JsGlobal::parse(global, raw))
+                .collect::<Result<_, _>>()?,
+            name,
+        })
+    }
+}
+
+fn parse_signature_file(content: &str) -> Result<Vec<Signature>, Box<dyn std::error::Error>> {
+    let raw: BTreeMap<String, RawSignature> = serde_json::from_str(content)?;
+    raw.into_iter()
+        .map(|(name, signature)| {
+            Signature::compile(name.clone(), signature)
+                .map_err(|e| format!("invalid pattern in signature '{}': {}", name, e).into())
+        })
+        .collect()
+}
+
+//Loads the built-in signatures plus an optional custom file, whose entries replace
+//built-in ones with the same name. Returns the number of signatures in use.
+pub fn load_signatures(custom: Option<&Path>) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut signatures = parse_signature_file(BUILTIN_SIGNATURES)?;
+    if let Some(path) = custom {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read signature file {}: {}", path.display(), e))?;
+        let custom_signatures = parse_signature_file(&content)
+            .map_err(|e| format!("signature file {}: {}", path.display(), e))?;
+        signatures.retain(|builtin| !custom_signatures.iter().any(|own| own.name == builtin.name));
+        signatures.extend(custom_signatures);
+    }
+    let count = signatures.len();
+    SIGNATURES
+        .set(signatures)
+        .map_err(|_| "technology signatures were already loaded")?;
+    Ok(count)
+}
+
+fn signatures() -> &'static [Signature] {
+    SIGNATURES.get_or_init(|| parse_signature_file(BUILTIN_SIGNATURES).unwrap_or_default())
+}
+
+//a detected technology, confidence is 0-100 and adds up over matching patterns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Technology {
+    pub name: String,
+    pub version: Option<String>,
+    pub categories: Vec<String>,
+    pub confidence: u8,
+}
+
+impl Technology {
+    //"jQuery 3.6.0", the value stored in the technologies category
+    pub fn label(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{} {}", self.name, version),
+            None => self.name.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Technology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())?;
+        if !self.categories.is_empty() {
+            write!(f, " [{}]", self.categories.join(", "))?;
+        }
+        write!(f, " (confidence {}%)", self.confidence)
+    }
+}
+
+//everything a page or response exposes to the signatures, unused parts stay empty
+#[derive(Debug, Default)]
+pub struct Evidence<'a> {
+    pub html: &'a str,
+    pub script_urls: Vec<String>,
+    //(lowercased name, content)
+    pub meta: Vec<(String, String)>,
+    //the bodies of inline <script> elements
+    pub inline_scripts: String,
+    pub headers: &'a [(String, String)],
+}
+
+impl<'a> Evidence<'a> {
+    pub fn from_document(html: &'a str, document: &Html) -> Self {
+        let script_selector = Selector::parse("script").unwrap();
+        let meta_selector = Selector::parse("meta[content]").unwrap();
+
+        let mut evidence = Evidence {
+            html,
+            ..Evidence::default()
+        };
+        for element in document.select(&script_selector) {
+            match element.value().attr("src") {
+                Some(src) => evidence.script_urls.push(src.to_string()),
+                None => {
+                    evidence.inline_scripts.extend(element.text());
+                    evidence.inline_scripts.push('\n');
+                }
+            }
+        }
+        for element in document.select(&meta_selector) {
+            let name = element
+                .value()
+                .attr("name")
+                .or_else(|| element.value().attr("property"))
+                .or_else(|| element.value().attr("http-equiv"));
+            if let (Some(name), Some(content)) = (name, element.value().attr("content")) {
+                evidence.meta.push((name.to_ascii_lowercase(), content.to_string()));
+            }
+        }
+        evidence
+    }
+
+    pub fn from_headers(headers: &'a [(String, String)]) -> Self {
+        Evidence {
+            headers,
+            ..Evidence::default()
+        }
+    }
+
+    fn header_values(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.headers
+            .iter()
+            .filter(move |(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim())
+    }
+
+    fn cookies(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.header_values("set-cookie").filter_map(|cookie| {
+            let pair = cookie.split(';').next().unwrap_or("");
+            pair.split_once('=').map(|(name, value)| (name.trim(), value.trim()))
+        })
+    }
+}
+
+#[derive(Default)]
+struct Hit {
+    version: Option<String>,
+    confidence: u32,
+}
+
+impl Hit {
+    fn add(&mut self, version: Option<String>, confidence: u8) {
+        self.confidence += u32::from(confidence);
+        //prefer the most specific version string seen
+        if let Some(version) = version
+            && self.version.as_ref().is_none_or(|current| version.len() > current.len())
+        {
+            self.version = Some(version);
+        }
+    }
+}
+
+pub fn detect(evidence: &Evidence) -> Vec<Technology> {
+    let mut detected = Vec::new();
+
+    for signature in signatures() {
+        let mut hit: Option<Hit> = None;
+        let mut record = |result: Option<Option<String>>, pattern: &Pattern| {
+            if let Some(version) = result {
+                hit.get_or_insert_with(Hit::default).add(version, pattern.confidence);
+            }
+        };
+
+        for pattern in &signature.scripts {
+            if let Some(result) = evidence.script_urls.iter().find_map(|src| pattern.matches(src)) {
+                record(Some(result), pattern);
+            }
+        }
+        if !evidence.html.is_empty() {
+            for pattern in &signature.html {
+                record(pattern.matches(evidence.html), pattern);
+            }
+        }
+        for (name, pattern) in &signature.meta {
+            if let Some(result) = evidence
+                .meta
+                .iter()
+                .filter(|(meta_name, _)| meta_name == name)
+                .find_map(|(_, content)| pattern.matches(content))
+            {
+                record(Some(result), pattern);
+            }
+        }
+        for (name, pattern) in &signature.headers {
+            if let Some(result) = evidence.header_values(name).find_map(|value| pattern.matches(value)) {
+                record(Some(result), pattern);
+            }
+        }
+        for (name, pattern) in &signature.cookies {
+            if let Some(result) = evidence
+                .cookies()
+                .filter(|(cookie, _)| cookie.eq_ignore_ascii_case(name))
+                .find_map(|(_, value)| pattern.matches(value))
+            {
+                record(Some(result), pattern);
+            }
+        }
+        for global in &signature.js {
+            record(global.matches(&evidence.inline_scripts), &global.pattern);
+        }
+
+        if let Some(hit) = hit {
+            detected.push(Technology {
+                name: signature.name.clone(),
+                version: hit.version,
+                categories: signature.categories.clone(),
+                confidence: hit.confidence.min(100) as u8,
+            });
+        }
+    }
+
+    detected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detected(evidence: &Evidence, name: &str) -> Option<Technology> {
+        detect(evidence).into_iter().find(|technology| technology.name == name)
+    }
+
+    #[test]
+    fn patterns_carry_their_version_template_and_confidence() {
+        let pattern = Pattern::parse(r"jquery[.-]([\d.]+)\.js\;version:\1\;confidence:50").unwrap();
+        assert_eq!(pattern.confidence, 50);
+        assert_eq!(pattern.matches("/js/JQuery-3.7.1.js"), Some(Some("3.7.1".to_string())));
+        assert_eq!(pattern.matches("/js/app.js"), None);
+        //a template that resolves to nothing is no version
+        let optional = Pattern::parse(r"nginx(?:/([\d.]+))?\;version:\1").unwrap();
+        assert_eq!(optional.matches("nginx"), Some(None));
+    }
+
+    #[test]
+    fn version_ternaries_pick_a_branch_by_group() {
+        let pattern = Pattern::parse(r"^IIS(?:/([\d.]+))?( beta)?\;version:\2?\1-beta:\1").unwrap();
+        assert_eq!(pattern.matches("IIS/10.0 beta"), Some(Some("10.0-beta".to_string())));
+        assert_eq!(pattern.matches("IIS/10.0"), Some(Some("10.0".to_string())));
+    }
+
+    #[test]
+    fn pages_are_matched_by_scripts_meta_and_js_globals() {
+        let html = r#"<html><head>
+<meta name="Generator" content="WordPress 6.4.2">
+<script src="/wp-includes/js/jquery/jquery-3.7.1.min.js"></script>
+<script>var Drupal = {}; React.version = "18.2.0";</script>
+</head></html>"#;
+        let evidence = Evidence::from_document(html, &Html::parse_document(html));
+
+        let wordpress = detected(&evidence, "WordPress").unwrap();
+        assert_eq!(wordpress.label(), "WordPress 6.4.2");
+        assert_eq!(wordpress.confidence, 100);
+        assert_eq!(detected(&evidence, "jQuery").unwrap().version.as_deref(), Some("3.7.1"));
+        assert_eq!(detected(&evidence, "React").unwrap().version.as_deref(), Some("18.2.0"));
+        assert!(detected(&evidence, "Drupal").is_some());
+        assert!(detected(&evidence, "Shopify").is_none());
+    }
+
+    #[test]
+    fn js_globals_must_stand_on_their_own() {
+        let html = "<script>window.Drupal = {}; var myLaravel = 1;</script>";
+        let evidence = Evidence::from_document(html, &Html::parse_document(html));
+        assert!(detected(&evidence, "Drupal").is_none());
+        assert!(detected(&evidence, "Laravel").is_none());
+    }
+
+    #[test]
+    fn headers_and_cookies_are_matched_case_insensitively() {
+        let headers = vec![
+            ("server".to_string(), "nginx/1.25.3".to_string()),
+            ("X-Powered-By".to_string(), "PHP/8.2.1".to_string()),
+            ("set-cookie".to_string(), "laravel_session=abc; path=/; HttpOnly".to_string()),
+        ];
+        let evidence = Evidence::from_headers(&headers);
+        assert_eq!(detected(&evidence, "Nginx").unwrap().label(), "Nginx 1.25.3");
+        assert_eq!(detected(&evidence, "PHP").unwrap().label(), "PHP 8.2.1");
+        assert!(detected(&evidence, "Laravel").is_some());
+        assert!(detected(&evidence, "Apache").is_none());
+    }
+}
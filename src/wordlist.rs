@@ -0,0 +1,496 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader};
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::rc::Rc;
+use flate2::read::MultiGzDecoder;
+use lru::LruCache;
+
+//placeholder of the first wordlist, further lists get FUZZ2, FUZZ3, ... unless named with FILE:KEYWORD
+pub const DEFAULT_KEYWORD: &str = "FUZZ";
+
+//wordlist name that reads from stdin
+const STDIN: &str = "-";
+
+//how many generated paths are remembered for de-duplication; a duplicate further apart is probed twice
+const DEDUP_WINDOW: usize = 1_000_000;
+
+//backup and editor leftovers of a file name, `{}` stands for the name;
+//'#' and spaces are percent-encoded since they would end or break the path
+const BACKUP_PATTERNS: &[&str] = &[
+    "{}~",
+    "{}.bak",
+    "{}.old",
+    "{}.orig",
+    "{}.save",
+    "{}.tmp",
+    "{}.swp",
+    ".{}.swp",
+    "%23{}%23",
+    "_{}",
+    "Copy%20of%20{}",
+];
+
+//a wordlist and the placeholder its words are put in
+#[derive(Debug, Clone)]
+pub struct WordlistSpec {
+    pub path: String,
+    pub keyword: String,
+}
+
+impl WordlistSpec {
+    pub fn placeholder(&self) -> String {
+        format!("{{{}}}", self.keyword)
+    }
+
+    pub fn is_stdin(&self) -> bool {
+        self.path == STDIN
+    }
+}
+
+fn is_keyword(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+//"-d dirs.txt -d files.txt:FILE" into lists with their keywords; a FILE:KEYWORD suffix is only
+//taken as such when the whole value is not an existing file
+pub fn parse_wordlist_specs(values: &[String]) -> Vec<WordlistSpec> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let named = value
+                .rsplit_once(':')
+                .filter(|(path, keyword)| !path.is_empty() && is_keyword(keyword) && !Path::new(value).exists());
+            match named {
+                Some((path, keyword)) => WordlistSpec {
+                    path: path.to_string(),
+                    keyword: keyword.to_string(),
+                },
+                None if index == 0 => WordlistSpec {
+                    path: value.clone(),
+                    keyword: DEFAULT_KEYWORD.to_string(),
+                },
+                None => WordlistSpec {
+                    path: value.clone(),
+                    keyword: format!("{}{}", DEFAULT_KEYWORD, index + 1),
+                },
+            }
+        })
+        .collect()
+}
+
+//Splits a target URL with placeholders ("https://example.com/api/{FUZZ}.json") into the base URL,
+//up to the path segment with the first placeholder, and the path template probed below it.
+//Returns no template when the URL has no placeholder.
+pub fn split_template(url: &str, specs: &[WordlistSpec]) -> Result<(String, Option<String>), String> {
+    let Some(first) = specs.iter().filter_map(|spec| url.find(&spec.placeholder())).min() else {
+        if specs.len() > 1 {
+            return Err(format!(
+                "several wordlists are combined, put their placeholders into the URL (e.g. {}/{}/{})",
+                url.trim_end_matches('/'),
+                specs[0].placeholder(),
+                specs[1].placeholder()
+            ));
+        }
+        return Ok((url.to_string(), None));
+    };
+    let authority_start = url.find("://").map_or(0, |index| index + 3);
+    let before = &url[..first];
+    let before = before.split_once('?').map_or(before, |(path, _)| path);
+    let slash = before
+        .rfind('/')
+        .filter(|slash| *slash >= authority_start)
+        .ok_or("placeholders are only supported in the path and query of the URL")?;
+
+    let template = &url[slash..];
+    if let Some(unused) = specs.iter().find(|spec| !template.contains(&spec.placeholder())) {
+        return Err(format!("the URL has no {} placeholder for {}", unused.placeholder(), unused.path));
+    }
+    Ok((url[..=slash].to_string(), Some(template.to_string())))
+}
+
+//Variants generated from every word of the first wordlist: case variants first, then
+//extensions, then backup names of each of them.
+#[derive(Debug, Clone, Default)]
+pub struct Mutations {
+    pub extensions: Vec<String>,
+    pub backups: bool,
+    pub case_variants: bool,
+}
+
+impl Mutations {
+    pub fn apply(&self, word: &str) -> Vec<String> {
+        let mut words = vec![word.to_string()];
+        if self.case_variants {
+            //only the last segment is changed, "admin/Login" becomes "admin/login", "admin/LOGIN", ...
+            let (stem, slash) = match word.strip_suffix('/') {
+                Some(stem) => (stem, "/"),
+                None => (word, ""),
+            };
+            let (dir, name) = split_name(stem);
+            let mut capitalized = name.chars();
+            let capitalized = match capitalized.next() {
+                Some(first) => first.to_uppercase().chain(capitalized.flat_map(char::to_lowercase)).collect(),
+                None => String::new(),
+            };
+            for variant in [name.to_lowercase(), name.to_uppercase(), capitalized] {
+                push_unique(&mut words, format!("{}{}{}", dir, variant, slash));
+            }
+        }
+
+        //a directory ("admin/") gets neither extensions nor backup names
+        if word.ends_with('/') {
+            return words;
+        }
+        if !self.extensions.is_empty() {
+            let bare = words.clone();
+            for word in &bare {
+                for extension in &self.extensions {
+                    push_unique(&mut words, format!("{}.{}", word, extension));
+                }
+            }
+        }
+        if self.backups {
+            let originals = words.clone();
+            for word in &originals {
+                let (dir, name) = split_name(word);
+                for pattern in BACKUP_PATTERNS {
+                    push_unique(&mut words, format!("{}{}", dir, pattern.replace("{}", name)));
+                }
+            }
+        }
+        words
+    }
+}
+
+//"admin/login.php" into "admin/" and "login.php"
+fn split_name(word: &str) -> (&str, &str) {
+    match word.rfind('/') {
+        Some(slash) => word.split_at(slash + 1),
+        None => ("", word),
+    }
+}
+
+fn push_unique(words: &mut Vec<String>, word: String) {
+    if !words.contains(&word) {
+        words.push(word);
+    }
+}
+
+//clap value parser for the entries of -x, ".bak" and "bak" are the same extension
+pub fn parse_extension(value: &str) -> Result<String, String> {
+    let extension = value.trim().trim_start_matches('.');
+    if extension.is_empty() || extension.contains('/') {
+        return Err(format!("'{}' is not a file extension", value));
+    }
+    Ok(extension.to_string())
+}
+
+//A wordlist file, or stdin for "-"; gzip-compressed input is recognized by its magic bytes,
+//so "list.txt.gz" and "zcat -f" style pipes both work.
+fn open_wordlist(spec: &WordlistSpec) -> Result<Box<dyn BufRead>, io::Error> {
+    let mut reader: Box<dyn BufRead> = if spec.is_stdin() {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        Box::new(BufReader::new(File::open(&spec.path)?))
+    };
+    if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        reader = Box::new(BufReader::new(MultiGzDecoder::new(reader)));
+    }
+    Ok(reader)
+}
+
+//The non-empty, non-comment lines of a wordlist, read as they are needed. Lines that are not
+//valid UTF-8 (Latin-1 lists are common) are kept with the invalid bytes replaced; the lines end
+//at the first read error, which is handed on as the last item.
+fn read_words(spec: &WordlistSpec) -> Result<impl Iterator<Item = io::Result<String>> + use<>, io::Error> {
+    let mut reader = open_wordlist(spec)?;
+    let mut failed = false;
+    let lines = std::iter::from_fn(move || {
+        if failed {
+            return None;
+        }
+        let mut line = Vec::new();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(String::from_utf8_lossy(&line).trim().to_string())),
+            Err(e) => {
+                failed = true;
+                Some(Err(e))
+            }
+        }
+    });
+    Ok(lines.filter(|line| line.as_ref().map_or(true, |line| !line.is_empty() && !line.starts_with('#'))))
+}
+
+//Every combination of one word from each list, in odometer order: the last list changes fastest.
+struct Combinations {
+    lists: Rc<Vec<Vec<String>>>,
+    indices: Vec<usize>,
+    done: bool,
+}
+
+impl Combinations {
+    fn new(lists: Rc<Vec<Vec<String>>>) -> Self {
+        let done = lists.iter().any(Vec::is_empty);
+        let indices = vec![0; lists.len()];
+        Combinations { lists, indices, done }
+    }
+}
+
+impl Iterator for Combinations {
+    type Item = Vec<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let combination = self
+            .indices
+            .iter()
+            .zip(self.lists.iter())
+            .map(|(index, list)| list[*index].clone())
+            .collect();
+
+        self.done = true;
+        for position in (0..self.indices.len()).rev() {
+            self.indices[position] += 1;
+            if self.indices[position] < self.lists[position].len() {
+                self.done = false;
+                break;
+            }
+            self.indices[position] = 0;
+        }
+        Some(combination)
+    }
+}
+
+//A wordlist word as a path below the base URL, with the leading slash the base URL is joined with.
+fn word_path(word: &str) -> String {
+    if word.starts_with('/') {
+        word.to_string()
+    } else {
+        format!("/{}", word)
+    }
+}
+
+//Generates the wordlist paths lazily: the first list is read line by line and each word is
+//mutated; with a template, every variant is combined with every word of the further lists,
+//which are held in memory (so the largest list should come first). A read error of the first
+//list ends the paths with that error.
+pub fn generate_paths(
+    specs: &[WordlistSpec],
+    template: Option<&str>,
+    mutations: &Mutations,
+) -> Result<Box<dyn Iterator<Item = io::Result<String>>>, io::Error> {
+    let Some((primary, others)) = specs.split_first() else {
+        return Ok(Box::new(std::iter::empty()));
+    };
+    let mutations = mutations.clone();
+    let words = read_words(primary)?.flat_map(move |word| match word {
+        Ok(word) => mutations.apply(&word).into_iter().map(Ok).collect(),
+        Err(e) => vec![Err(e)],
+    });
+
+    let Some(template) = template else {
+        return Ok(Box::new(words.map(|word| word.map(|word| word_path(&word)))));
+    };
+    let template = template.to_string();
+    let placeholders: Rc<Vec<String>> = Rc::new(others.iter().map(WordlistSpec::placeholder).collect());
+    let lists = others
+        .iter()
+        .map(|spec| read_words(spec)?.collect())
+        .collect::<Result<Vec<Vec<String>>, io::Error>>()?;
+    let lists = Rc::new(lists);
+    let primary_placeholder = primary.placeholder();
+
+    Ok(Box::new(words.flat_map(move |word| -> Box<dyn Iterator<Item = io::Result<String>>> {
+        let word = match word {
+            Ok(word) => word,
+            Err(e) => return Box::new(std::iter::once(Err(e))),
+        };
+        let path = template.replace(&primary_placeholder, &word);
+        let placeholders = Rc::clone(&placeholders);
+        Box::new(Combinations::new(Rc::clone(&lists)).map(move |combination| {
+            Ok(placeholders
+                .iter()
+                .zip(&combination)
+                .fold(path.clone(), |path, (placeholder, word)| path.replace(placeholder, word)))
+        }))
+    })))
+}
+
+//The number of paths `generate_paths` yields, duplicates included; None for stdin, which can only be read once.
+pub fn count_paths(specs: &[WordlistSpec], template: Option<&str>, mutations: &Mutations) -> Result<Option<u64>, io::Error> {
+    if specs.iter().any(WordlistSpec::is_stdin) {
+        return Ok(None);
+    }
+    let mut count = 0;
+    for path in generate_paths(specs, template, mutations)? {
+        path?;
+        count += 1;
+    }
+    Ok(Some(count))
+}
+
+//Paths already handed out, for dropping duplicates from a stream of millions of paths. Only a
+//hash of the most recent ones is kept, so memory stays bounded however long the wordlist is.
+pub struct SeenPaths {
+    recent: LruCache<u64, ()>,
+}
+
+impl Default for SeenPaths {
+    fn default() -> Self {
+        SeenPaths {
+            recent: LruCache::new(NonZeroUsize::new(DEDUP_WINDOW).unwrap()),
+        }
+    }
+}
+
+impl SeenPaths {
+    //true when `path` was not seen before
+    pub fn insert(&mut self, path: &str) -> bool {
+        let mut hasher = DefaultHasher::new();
+        path.hash(&mut hasher);
+        self.recent.put(hasher.finish(), ()).is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    //a wordlist in the temp directory, removed when dropped
+    struct TempList(PathBuf);
+
+    impl TempList {
+        fn new(name: &str, content: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("wordlist-test-{}-{}", std::process::id(), name));
+            fs::write(&path, content).unwrap();
+            TempList(path)
+        }
+
+        fn spec(&self) -> WordlistSpec {
+            WordlistSpec {
+                path: self.0.to_string_lossy().into_owned(),
+                keyword: DEFAULT_KEYWORD.to_string(),
+            }
+        }
+    }
+
+    impl Drop for TempList {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn paths(specs: &[WordlistSpec], template: Option<&str>, mutations: &Mutations) -> Vec<String> {
+        generate_paths(specs, template, mutations)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn invalid_utf8_lines_do_not_end_the_wordlist() {
+        let list = TempList::new("latin1", b"admin\nsecci\xf3n\n# comment\n\nlogin\n");
+        let specs = [list.spec()];
+        assert_eq!(paths(&specs, None, &Mutations::default()), ["/admin", "/secci\u{fffd}n", "/login"]);
+        assert_eq!(count_paths(&specs, None, &Mutations::default()).unwrap(), Some(3));
+    }
+
+    fn spec(path: &str, keyword: &str) -> WordlistSpec {
+        WordlistSpec {
+            path: path.to_string(),
+            keyword: keyword.to_string(),
+        }
+    }
+
+    #[test]
+    fn specs_get_numbered_keywords_unless_named() {
+        let specs = parse_wordlist_specs(&["dirs.txt".into(), "files.txt".into(), "actions.txt:ACTION".into()]);
+        let keywords: Vec<&str> = specs.iter().map(|spec| spec.keyword.as_str()).collect();
+        assert_eq!(keywords, ["FUZZ", "FUZZ2", "ACTION"]);
+        assert_eq!(specs[2].path, "actions.txt");
+        //a lowercase suffix is part of the file name, "-" reads stdin whatever it is named
+        assert_eq!(parse_wordlist_specs(&["c:lists".into()])[0].path, "c:lists");
+        assert!(parse_wordlist_specs(&["-:WORD".into()])[0].is_stdin());
+    }
+
+    #[test]
+    fn templates_start_at_the_segment_of_the_first_placeholder() {
+        let specs = [spec("a.txt", "FUZZ")];
+        assert_eq!(
+            split_template("https://example.com/api/{FUZZ}.json", &specs).unwrap(),
+            ("https://example.com/api/".to_string(), Some("/{FUZZ}.json".to_string()))
+        );
+        assert_eq!(
+            split_template("https://example.com/search?q={FUZZ}", &specs).unwrap(),
+            ("https://example.com/".to_string(), Some("/search?q={FUZZ}".to_string()))
+        );
+        assert_eq!(
+            split_template("https://example.com/", &specs).unwrap(),
+            ("https://example.com/".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn templates_reject_misplaced_or_missing_placeholders() {
+        let one = [spec("a.txt", "FUZZ")];
+        let two = [spec("a.txt", "FUZZ"), spec("b.txt", "ACTION")];
+        assert!(split_template("https://{FUZZ}.example.com/", &one).is_err());
+        assert!(split_template("https://example.com/", &two).is_err());
+        assert!(split_template("https://example.com/{FUZZ}/", &two).is_err());
+        assert!(split_template("https://example.com/{FUZZ}/{ACTION}", &two).is_ok());
+    }
+
+    #[test]
+    fn mutations_add_case_variants_then_extensions_then_backups() {
+        let mutations = Mutations {
+            extensions: vec!["php".to_string()],
+            backups: true,
+            case_variants: true,
+        };
+        let words = mutations.apply("admin/Login");
+        assert_eq!(&words[..6], ["admin/Login", "admin/login", "admin/LOGIN", "admin/Login.php", "admin/login.php", "admin/LOGIN.php"]);
+        assert!(words.contains(&"admin/Login.php~".to_string()));
+        assert!(words.contains(&"admin/.login.php.swp".to_string()));
+        assert!(words.contains(&"admin/%23LOGIN%23".to_string()));
+        //no duplicates, and directories only get their case variants
+        assert_eq!(words.len(), words.iter().collect::<std::collections::HashSet<_>>().len());
+        assert_eq!(mutations.apply("Static/"), ["Static/", "static/", "STATIC/"]);
+    }
+
+    #[test]
+    fn extensions_are_given_with_or_without_their_dot() {
+        assert_eq!(parse_extension(".bak").unwrap(), "bak");
+        assert_eq!(parse_extension(" php ").unwrap(), "php");
+        assert!(parse_extension(".").is_err());
+        assert!(parse_extension("a/b").is_err());
+    }
+
+    #[test]
+    fn combined_wordlists_yield_every_combination() {
+        let dirs = TempList::new("dirs", b"api\nv2\n");
+        let actions = TempList::new("actions", b"list\nshow\n");
+        let specs = [dirs.spec(), WordlistSpec { keyword: "ACTION".to_string(), ..actions.spec() }];
+        assert_eq!(
+            paths(&specs, Some("/{FUZZ}/{ACTION}"), &Mutations::default()),
+            ["/api/list", "/api/show", "/v2/list", "/v2/show"]
+        );
+        assert_eq!(count_paths(&specs, Some("/{FUZZ}/{ACTION}"), &Mutations::default()).unwrap(), Some(4));
+    }
+
+    #[test]
+    fn seen_paths_drop_repeats() {
+        let mut seen = SeenPaths::default();
+        assert!(seen.insert("/admin"));
+        assert!(seen.insert("/login"));
+        assert!(!seen.insert("/admin"));
+    }
+}